@@ -6,7 +6,11 @@ pub fn opts() -> TargetOptions {
         has_thread_local: true,
         // linker: Some("rust-lld".into()),
         linker_flavor: LinkerFlavor::Gnu(Cc::No, Lld::Yes),
-        panic_strategy: PanicStrategy::Abort,
+        // the kernel loads binaries at a random address, and std's `_start`
+        // applies the relocations itself
+        position_independent_executables: true,
+        static_position_independent_executables: true,
+        panic_strategy: PanicStrategy::Unwind,
         // singlethread: true,
         stack_probes: StackProbeType::Inline,
         tls_model: TlsModel::InitialExec,
@@ -1595,7 +1595,11 @@ fn $module() {
     ("riscv64gc-unknown-hermit", riscv64gc_unknown_hermit),
     ("x86_64-unknown-hermit", x86_64_unknown_hermit),
 
+    ("aarch64-unknown-hyperion", aarch64_unknown_hyperion),
+    ("riscv64gc-unknown-hyperion", riscv64gc_unknown_hyperion),
     ("x86_64-unknown-hyperion", x86_64_unknown_hyperion),
+    ("x86_64-unknown-hyperion-hosted", x86_64_unknown_hyperion_hosted),
+    ("x86_64-unknown-hyperion-kernel", x86_64_unknown_hyperion_kernel),
 
     ("x86_64-unikraft-linux-musl", x86_64_unikraft_linux_musl),
 
@@ -0,0 +1,18 @@
+use crate::spec::{base, CodeModel, Target, TargetOptions};
+
+pub fn target() -> Target {
+    Target {
+        llvm_target: "riscv64-unknown-none".into(),
+        pointer_width: 64,
+        arch: "riscv64".into(),
+        data_layout: "e-m:e-p:64:64-i64:64-i128:128-n32:64-S128".into(),
+        options: TargetOptions {
+            cpu: "generic-rv64".into(),
+            features: "+m,+a,+f,+d,+c".into(),
+            code_model: Some(CodeModel::Medium),
+            max_atomic_width: Some(64),
+            llvm_abiname: "lp64d".into(),
+            ..base::hyperion::opts()
+        },
+    }
+}
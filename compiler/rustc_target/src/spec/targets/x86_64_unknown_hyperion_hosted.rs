@@ -0,0 +1,29 @@
+use crate::spec::{base, Cc, LinkerFlavor, Lld, Target, TargetOptions};
+
+/// Hyperion's std running as a static Linux executable, on top of the
+/// `hyperion-abi-linux` shim instead of the Hyperion kernel. This lets the std
+/// test suite run on a Linux host.
+pub fn target() -> Target {
+    let mut base = base::hyperion::opts();
+    // the shim sets up TLS and converts the arguments before calling `_start`
+    base.add_pre_link_args(
+        LinkerFlavor::Gnu(Cc::No, Lld::Yes),
+        &["--entry=__hyperion_linux_start"],
+    );
+
+    Target {
+        llvm_target: "x86_64-unknown-linux-gnu".into(),
+        pointer_width: 64,
+        arch: "x86_64".into(),
+        data_layout:
+            "e-m:e-p270:32:32-p271:32:32-p272:64:64-i64:64-i128:128-f80:128-n8:16:32:64-S128".into(),
+        options: TargetOptions {
+            env: "hosted".into(),
+            cpu: "x86-64".into(),
+            max_atomic_width: Some(64),
+            // static-PIE like the real target, so Linux loads it at a random
+            // address and the relocation code runs in the std tests
+            ..base
+        },
+    }
+}
@@ -0,0 +1,37 @@
+// The Hyperion kernel itself: freestanding, `core` and `alloc` only.
+//
+// Kernel code runs in the top 2 GiB of the address space, can't touch the
+// FPU/SIMD state of the interrupted user code, and interrupts push onto the
+// kernel stack, which would clobber a red zone. `target_os` stays `none`: the
+// `hyperion` cfgs belong to userspace.
+
+use crate::spec::{Cc, CodeModel, LinkerFlavor, Lld, PanicStrategy};
+use crate::spec::{RelroLevel, SanitizerSet, StackProbeType, Target, TargetOptions};
+
+pub fn target() -> Target {
+    let opts = TargetOptions {
+        cpu: "x86-64".into(),
+        plt_by_default: false,
+        max_atomic_width: Some(64),
+        stack_probes: StackProbeType::Inline,
+        relro_level: RelroLevel::Full,
+        linker_flavor: LinkerFlavor::Gnu(Cc::No, Lld::Yes),
+        linker: Some("rust-lld".into()),
+        features:
+            "-mmx,-sse,-sse2,-sse3,-ssse3,-sse4.1,-sse4.2,-3dnow,-3dnowa,-avx,-avx2,+soft-float"
+                .into(),
+        supported_sanitizers: SanitizerSet::KCFI | SanitizerSet::KERNELADDRESS,
+        disable_redzone: true,
+        panic_strategy: PanicStrategy::Abort,
+        code_model: Some(CodeModel::Kernel),
+        ..Default::default()
+    };
+    Target {
+        llvm_target: "x86_64-unknown-none-elf".into(),
+        pointer_width: 64,
+        data_layout:
+            "e-m:e-p270:32:32-p271:32:32-p272:64:64-i64:64-i128:128-f80:128-n8:16:32:64-S128".into(),
+        arch: "x86_64".into(),
+        options: opts,
+    }
+}
@@ -1,4 +1,4 @@
-use crate::spec::Target;
+use crate::spec::{Target, TargetTriple};
 
 #[test]
 fn report_unused_fields() {
@@ -55,3 +55,60 @@ fn no_warnings_for_valid_target() {
     let warnings = Target::from_json(json).unwrap().1;
     assert_eq!(warnings.warning_messages().len(), 0);
 }
+
+#[test]
+fn hyperion_kernel_matches_its_json_spec() {
+    // the hand-written spec the Hyperion kernel used to be built with
+    let json = serde_json::from_str(
+        r#"
+    {
+        "arch": "x86_64",
+        "code-model": "kernel",
+        "cpu": "x86-64",
+        "crt-objects-fallback": "false",
+        "data-layout": "e-m:e-p270:32:32-p271:32:32-p272:64:64-i64:64-f80:128-n8:16:32:64-S128",
+        "disable-redzone": true,
+        "features": "-mmx,-sse,-sse2,-sse3,-ssse3,-sse4.1,-sse4.2,-3dnow,-3dnowa,-avx,-avx2,+soft-float",
+        "linker": "rust-lld",
+        "linker-flavor": "gnu-lld",
+        "llvm-target": "x86_64-unknown-none-elf",
+        "max-atomic-width": 64,
+        "panic-strategy": "abort",
+        "plt-by-default": false,
+        "relro-level": "full",
+        "stack-probes": {
+            "kind": "inline"
+        },
+        "supported-sanitizers": [
+            "kcfi",
+            "kernel-address"
+        ],
+        "target-pointer-width": "64"
+    }
+    "#,
+    )
+    .unwrap();
+    let (json, warnings) = Target::from_json(json).unwrap();
+    assert_eq!(warnings.warning_messages().len(), 0);
+
+    let builtin =
+        Target::expect_builtin(&TargetTriple::from_triple("x86_64-unknown-hyperion-kernel"));
+    // the JSON predates LLVM 18, which added `i128:128` to the x86_64 data layout
+    assert_eq!(&*builtin.data_layout, json.data_layout.replace("-i64:64-", "-i64:64-i128:128-"));
+    assert_eq!(builtin.llvm_target, json.llvm_target);
+    assert_eq!(builtin.os, json.os);
+    assert_eq!(builtin.cpu, json.cpu);
+    assert_eq!(builtin.features, json.features);
+    assert_eq!(builtin.code_model, json.code_model);
+    assert_eq!(builtin.disable_redzone, json.disable_redzone);
+    assert_eq!(builtin.panic_strategy, json.panic_strategy);
+    assert_eq!(builtin.supported_sanitizers, json.supported_sanitizers);
+    assert_eq!(builtin.stack_probes, json.stack_probes);
+    assert_eq!(builtin.relro_level, json.relro_level);
+    assert_eq!(builtin.plt_by_default, json.plt_by_default);
+    assert_eq!(builtin.max_atomic_width, json.max_atomic_width);
+    assert_eq!(builtin.linker, json.linker);
+    assert_eq!(builtin.linker_flavor, json.linker_flavor);
+    // `crt-objects-fallback` is what sets `link_self_contained` in JSON specs
+    assert_eq!(builtin.link_self_contained, json.link_self_contained);
+}
@@ -0,0 +1,173 @@
+//! A small slab allocator over whole pages, like the one in `hyperion-abi`.
+
+use core::alloc::{GlobalAlloc, Layout};
+use core::cell::UnsafeCell;
+use core::marker::PhantomData;
+use core::ptr;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use crate::linux::PAGE_SIZE;
+
+//
+
+/// Where a [`SlabAllocator`] gets its pages from.
+///
+/// # Safety
+///
+/// `alloc` has to return `pages` fresh pages or a null [`Pages`], and
+/// `dealloc` has to accept everything `alloc` returned.
+pub unsafe trait PageAlloc {
+    /// # Safety
+    ///
+    /// The pages are owned by the caller until they are given to `dealloc`.
+    unsafe fn alloc(pages: usize) -> Pages;
+
+    /// # Safety
+    ///
+    /// `frames` has to come from `alloc` and may not be used anymore.
+    unsafe fn dealloc(frames: Pages);
+}
+
+/// A run of whole pages.
+#[derive(Debug)]
+pub struct Pages {
+    ptr: *mut u8,
+    len: usize,
+}
+
+impl Pages {
+    /// # Safety
+    ///
+    /// `ptr` has to point to `pages` owned pages, or be null.
+    pub unsafe fn new(ptr: *mut u8, pages: usize) -> Self {
+        Self { ptr, len: pages }
+    }
+
+    pub fn as_ptr(&self) -> *mut u8 {
+        self.ptr
+    }
+
+    /// The number of pages.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+//
+
+/// The smallest size class, every free slot has to fit a pointer.
+const MIN_SIZE: usize = 16;
+/// `16, 32, ..., 4096`
+const CLASSES: usize = 9;
+
+/// Allocates from free lists of power-of-two sized slots, each refilled a page
+/// at a time. Pages are never returned to `P`.
+pub struct SlabAllocator<P> {
+    slabs: [Slab; CLASSES],
+    _p: PhantomData<P>,
+}
+
+unsafe impl<P> Sync for SlabAllocator<P> {}
+
+impl<P> SlabAllocator<P> {
+    pub const fn new() -> Self {
+        #[allow(clippy::declare_interior_mutable_const)]
+        const EMPTY: Slab = Slab::new();
+        Self { slabs: [EMPTY; CLASSES], _p: PhantomData }
+    }
+}
+
+impl<P> Default for SlabAllocator<P> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<P: PageAlloc> SlabAllocator<P> {
+    fn slab(&self, layout: Layout) -> Option<(&Slab, usize)> {
+        let size = layout.size().max(layout.align()).max(MIN_SIZE).next_power_of_two();
+        let class = (size.trailing_zeros() - MIN_SIZE.trailing_zeros()) as usize;
+        self.slabs.get(class).map(|slab| (slab, size))
+    }
+}
+
+unsafe impl<P: PageAlloc> GlobalAlloc for SlabAllocator<P> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        match self.slab(layout) {
+            Some((slab, size)) => slab.pop::<P>(size),
+            None => ptr::null_mut(),
+        }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        if let Some((slab, _)) = self.slab(layout) {
+            // SAFETY: the caller gives back a slot of this size class
+            unsafe { slab.push(ptr) };
+        }
+    }
+}
+
+//
+
+/// One size class, a spinlocked list of free slots.
+struct Slab {
+    lock: AtomicBool,
+    free: UnsafeCell<*mut u8>,
+}
+
+impl Slab {
+    const fn new() -> Self {
+        Self { lock: AtomicBool::new(false), free: UnsafeCell::new(ptr::null_mut()) }
+    }
+
+    fn with<T>(&self, f: impl FnOnce(&mut *mut u8) -> T) -> T {
+        while self.lock.swap(true, Ordering::Acquire) {
+            core::hint::spin_loop();
+        }
+        // SAFETY: the lock is held
+        let res = f(unsafe { &mut *self.free.get() });
+        self.lock.store(false, Ordering::Release);
+        res
+    }
+
+    fn pop<P: PageAlloc>(&self, size: usize) -> *mut u8 {
+        self.with(|free| {
+            if free.is_null() {
+                // SAFETY: the new page is owned by this slab from now on
+                let page = unsafe { P::alloc(1) };
+                if page.as_ptr().is_null() {
+                    return ptr::null_mut();
+                }
+                // thread the page's slots into the free list
+                for slot in (0..PAGE_SIZE).step_by(size).rev() {
+                    // SAFETY: every slot is within the page
+                    unsafe {
+                        let slot = page.as_ptr().add(slot);
+                        *(slot as *mut *mut u8) = *free;
+                        *free = slot;
+                    }
+                }
+            }
+
+            let slot = *free;
+            // SAFETY: free slots store the next free slot
+            *free = unsafe { *(slot as *mut *mut u8) };
+            slot
+        })
+    }
+
+    /// # Safety
+    ///
+    /// `slot` has to be a slot of this slab that isn't in use.
+    unsafe fn push(&self, slot: *mut u8) {
+        self.with(|free| {
+            // SAFETY: the slot is free and fits a pointer
+            unsafe { *(slot as *mut *mut u8) = *free };
+            *free = slot;
+        })
+    }
+}
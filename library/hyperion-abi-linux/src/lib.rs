@@ -0,0 +1,14 @@
+//! The `hyperion-abi` syscall surface, implemented on top of Linux.
+//!
+//! Every function maps onto one or a few raw x86_64 Linux syscalls, and every
+//! type mirrors the one `std`'s Hyperion PAL uses from the real `hyperion-abi`.
+//! Linux errnos are translated into Hyperion [`sys::err::Error`]s, so the PAL
+//! sees the same errors it would see on the real kernel.
+
+#![no_std]
+#![deny(unsafe_op_in_unsafe_fn)]
+
+pub mod alloc;
+mod linux;
+mod start;
+pub mod sys;
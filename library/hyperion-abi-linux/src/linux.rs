@@ -0,0 +1,383 @@
+//! Raw x86_64 Linux syscalls and the constants the shim needs.
+
+use core::arch::asm;
+
+use crate::sys::err::{Error, Result};
+
+//
+
+pub const READ: usize = 0;
+pub const WRITE: usize = 1;
+pub const CLOSE: usize = 3;
+pub const FSTAT: usize = 5;
+pub const LSEEK: usize = 8;
+pub const MMAP: usize = 9;
+pub const MPROTECT: usize = 10;
+pub const MUNMAP: usize = 11;
+pub const RT_SIGACTION: usize = 13;
+pub const RT_SIGPROCMASK: usize = 14;
+pub const IOCTL: usize = 16;
+pub const PREAD64: usize = 17;
+pub const PWRITE64: usize = 18;
+pub const READV: usize = 19;
+pub const WRITEV: usize = 20;
+pub const ACCESS: usize = 21;
+pub const SCHED_YIELD: usize = 24;
+pub const MREMAP: usize = 25;
+pub const DUP2: usize = 33;
+pub const GETPID: usize = 39;
+pub const SOCKET: usize = 41;
+pub const CONNECT: usize = 42;
+pub const SENDTO: usize = 44;
+pub const RECVFROM: usize = 45;
+pub const SHUTDOWN: usize = 48;
+pub const BIND: usize = 49;
+pub const LISTEN: usize = 50;
+pub const GETSOCKNAME: usize = 51;
+pub const GETPEERNAME: usize = 52;
+pub const SETSOCKOPT: usize = 54;
+pub const GETSOCKOPT: usize = 55;
+pub const CLONE: usize = 56;
+pub const EXECVE: usize = 59;
+pub const EXIT: usize = 60;
+pub const WAIT4: usize = 61;
+pub const KILL: usize = 62;
+pub const FCNTL: usize = 72;
+pub const FSYNC: usize = 74;
+pub const FTRUNCATE: usize = 77;
+pub const GETCWD: usize = 79;
+pub const CHDIR: usize = 80;
+pub const FCHMOD: usize = 91;
+pub const SIGALTSTACK: usize = 131;
+pub const PRCTL: usize = 157;
+pub const ARCH_PRCTL: usize = 158;
+pub const FUTEX: usize = 202;
+pub const SCHED_GETAFFINITY: usize = 204;
+pub const GETDENTS64: usize = 217;
+pub const CLOCK_GETTIME: usize = 228;
+pub const CLOCK_NANOSLEEP: usize = 230;
+pub const EXIT_GROUP: usize = 231;
+pub const OPENAT: usize = 257;
+pub const MKDIRAT: usize = 258;
+pub const NEWFSTATAT: usize = 262;
+pub const UNLINKAT: usize = 263;
+pub const RENAMEAT: usize = 264;
+pub const LINKAT: usize = 265;
+pub const SYMLINKAT: usize = 266;
+pub const READLINKAT: usize = 267;
+pub const FCHMODAT: usize = 268;
+pub const PPOLL: usize = 271;
+pub const UTIMENSAT: usize = 280;
+pub const ACCEPT4: usize = 288;
+pub const PIPE2: usize = 293;
+pub const GETRANDOM: usize = 318;
+
+pub const ENOTTY: usize = 25;
+pub const ERANGE: usize = 34;
+
+pub const AT_FDCWD: usize = -100isize as usize;
+pub const AT_REMOVEDIR: usize = 0x200;
+pub const AT_SYMLINK_NOFOLLOW: usize = 0x100;
+
+pub const O_RDONLY: usize = 0;
+pub const O_WRONLY: usize = 1;
+pub const O_RDWR: usize = 2;
+pub const O_CREAT: usize = 0o100;
+pub const O_EXCL: usize = 0o200;
+pub const O_TRUNC: usize = 0o1000;
+pub const O_APPEND: usize = 0o2000;
+pub const O_NONBLOCK: usize = 0o4000;
+pub const O_DIRECTORY: usize = 0o200000;
+pub const O_NOFOLLOW: usize = 0o400000;
+pub const O_CLOEXEC: usize = 0o2000000;
+pub const O_PATH: usize = 0o10000000;
+
+pub const F_SETFD: usize = 2;
+pub const F_GETFL: usize = 3;
+pub const F_SETFL: usize = 4;
+pub const F_DUPFD_CLOEXEC: usize = 1030;
+
+pub const S_IFMT: u32 = 0o170000;
+pub const S_IFDIR: u32 = 0o040000;
+pub const S_IFLNK: u32 = 0o120000;
+
+pub const PROT_NONE: usize = 0;
+pub const PROT_READ: usize = 1;
+pub const PROT_WRITE: usize = 2;
+pub const MAP_PRIVATE: usize = 0x02;
+pub const MAP_ANONYMOUS: usize = 0x20;
+pub const MREMAP_MAYMOVE: usize = 1;
+
+pub const CLOCK_REALTIME: usize = 0;
+pub const CLOCK_MONOTONIC: usize = 1;
+pub const TIMER_ABSTIME: usize = 1;
+
+// the futex ops with `FUTEX_PRIVATE_FLAG` set, threads never share futexes with other processes
+pub const FUTEX_WAIT_PRIVATE: usize = 128;
+pub const FUTEX_WAKE_PRIVATE: usize = 129;
+pub const FUTEX_WAIT_BITSET_PRIVATE: usize = 137;
+pub const FUTEX_BITSET_MATCH_ANY: usize = u32::MAX as usize;
+
+pub const SIGCHLD: usize = 17;
+pub const SIGKILL: usize = 9;
+
+pub const PAGE_SIZE: usize = 0x1000;
+pub const PATH_MAX: usize = 4096;
+
+//
+
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct Timespec {
+    pub sec: i64,
+    pub nsec: i64,
+}
+
+impl Timespec {
+    pub fn from_nanos(nanos: u64) -> Self {
+        Self { sec: (nanos / 1_000_000_000) as i64, nsec: (nanos % 1_000_000_000) as i64 }
+    }
+
+    pub fn as_nanos(&self) -> u64 {
+        (self.sec as u64).saturating_mul(1_000_000_000).saturating_add(self.nsec as u64)
+    }
+}
+
+/// `struct stat` on x86_64.
+#[repr(C)]
+#[derive(Default)]
+pub struct Stat {
+    pub dev: u64,
+    pub ino: u64,
+    pub nlink: u64,
+    pub mode: u32,
+    pub uid: u32,
+    pub gid: u32,
+    _pad0: u32,
+    pub rdev: u64,
+    pub size: i64,
+    pub blksize: i64,
+    pub blocks: i64,
+    pub atime: Timespec,
+    pub mtime: Timespec,
+    pub ctime: Timespec,
+    _reserved: [i64; 3],
+}
+
+//
+
+/// Converts a raw syscall return value, `-4095..0` are negated errnos.
+pub fn check(ret: usize) -> Result<usize> {
+    if ret > -4096isize as usize { Err(Error::from_errno(ret.wrapping_neg())) } else { Ok(ret) }
+}
+
+#[inline]
+pub unsafe fn syscall0(n: usize) -> usize {
+    let ret;
+    // SAFETY: the caller upholds the syscall's contract
+    unsafe {
+        asm!("syscall", inlateout("rax") n => ret, lateout("rcx") _, lateout("r11") _, options(nostack));
+    }
+    ret
+}
+
+#[inline]
+pub unsafe fn syscall1(n: usize, a1: usize) -> usize {
+    let ret;
+    // SAFETY: the caller upholds the syscall's contract
+    unsafe {
+        asm!(
+            "syscall",
+            inlateout("rax") n => ret,
+            in("rdi") a1,
+            lateout("rcx") _,
+            lateout("r11") _,
+            options(nostack),
+        );
+    }
+    ret
+}
+
+#[inline]
+pub unsafe fn syscall2(n: usize, a1: usize, a2: usize) -> usize {
+    let ret;
+    // SAFETY: the caller upholds the syscall's contract
+    unsafe {
+        asm!(
+            "syscall",
+            inlateout("rax") n => ret,
+            in("rdi") a1,
+            in("rsi") a2,
+            lateout("rcx") _,
+            lateout("r11") _,
+            options(nostack),
+        );
+    }
+    ret
+}
+
+#[inline]
+pub unsafe fn syscall3(n: usize, a1: usize, a2: usize, a3: usize) -> usize {
+    let ret;
+    // SAFETY: the caller upholds the syscall's contract
+    unsafe {
+        asm!(
+            "syscall",
+            inlateout("rax") n => ret,
+            in("rdi") a1,
+            in("rsi") a2,
+            in("rdx") a3,
+            lateout("rcx") _,
+            lateout("r11") _,
+            options(nostack),
+        );
+    }
+    ret
+}
+
+#[inline]
+pub unsafe fn syscall4(n: usize, a1: usize, a2: usize, a3: usize, a4: usize) -> usize {
+    let ret;
+    // SAFETY: the caller upholds the syscall's contract
+    unsafe {
+        asm!(
+            "syscall",
+            inlateout("rax") n => ret,
+            in("rdi") a1,
+            in("rsi") a2,
+            in("rdx") a3,
+            in("r10") a4,
+            lateout("rcx") _,
+            lateout("r11") _,
+            options(nostack),
+        );
+    }
+    ret
+}
+
+#[inline]
+pub unsafe fn syscall5(n: usize, a1: usize, a2: usize, a3: usize, a4: usize, a5: usize) -> usize {
+    let ret;
+    // SAFETY: the caller upholds the syscall's contract
+    unsafe {
+        asm!(
+            "syscall",
+            inlateout("rax") n => ret,
+            in("rdi") a1,
+            in("rsi") a2,
+            in("rdx") a3,
+            in("r10") a4,
+            in("r8") a5,
+            lateout("rcx") _,
+            lateout("r11") _,
+            options(nostack),
+        );
+    }
+    ret
+}
+
+#[inline]
+pub unsafe fn syscall6(
+    n: usize,
+    a1: usize,
+    a2: usize,
+    a3: usize,
+    a4: usize,
+    a5: usize,
+    a6: usize,
+) -> usize {
+    let ret;
+    // SAFETY: the caller upholds the syscall's contract
+    unsafe {
+        asm!(
+            "syscall",
+            inlateout("rax") n => ret,
+            in("rdi") a1,
+            in("rsi") a2,
+            in("rdx") a3,
+            in("r10") a4,
+            in("r8") a5,
+            in("r9") a6,
+            lateout("rcx") _,
+            lateout("r11") _,
+            options(nostack),
+        );
+    }
+    ret
+}
+
+//
+
+/// Runs `f` with a NUL terminated copy of `path`, Linux wants C strings.
+pub fn with_cstr<T>(path: &str, f: impl FnOnce(usize) -> Result<T>) -> Result<T> {
+    let mut buf = [0u8; PATH_MAX];
+    if path.len() >= buf.len() || path.as_bytes().contains(&0) {
+        return Err(Error::INVALID_ARGUMENT);
+    }
+    buf[..path.len()].copy_from_slice(path.as_bytes());
+    f(buf.as_ptr() as usize)
+}
+
+/// Retries a syscall that got interrupted by a signal.
+pub fn retry(mut f: impl FnMut() -> usize) -> Result<usize> {
+    loop {
+        match check(f()) {
+            Err(Error::INTERRUPTED) => continue,
+            ret => return ret,
+        }
+    }
+}
+
+pub fn mmap(len: usize) -> Result<usize> {
+    // SAFETY: an anonymous mapping doesn't alias anything
+    check(unsafe {
+        syscall6(MMAP, 0, len, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS, usize::MAX, 0)
+    })
+}
+
+/// # Safety
+///
+/// Nothing may use the mapping afterwards.
+pub unsafe fn munmap(addr: usize, len: usize) -> Result<()> {
+    // SAFETY: the caller guarantees that the mapping isn't used anymore
+    check(unsafe { syscall2(MUNMAP, addr, len) }).map(|_| ())
+}
+
+pub fn clock_gettime(clock: usize) -> Result<u64> {
+    let mut ts = Timespec::default();
+    // SAFETY: `ts` is valid for writes
+    check(unsafe { syscall2(CLOCK_GETTIME, clock, &mut ts as *mut Timespec as usize) })?;
+    Ok(ts.as_nanos())
+}
+
+pub fn getsockopt_int(fd: usize, level: usize, name: usize) -> Result<u32> {
+    let mut val = 0u32;
+    let mut len = core::mem::size_of::<u32>() as u32;
+    // SAFETY: `val` and `len` are valid for writes
+    check(unsafe {
+        syscall5(
+            GETSOCKOPT,
+            fd,
+            level,
+            name,
+            &mut val as *mut u32 as usize,
+            &mut len as *mut u32 as usize,
+        )
+    })?;
+    Ok(val)
+}
+
+pub fn setsockopt_int(fd: usize, level: usize, name: usize, val: u32) -> Result<()> {
+    // SAFETY: `val` is valid for reads
+    check(unsafe {
+        syscall5(
+            SETSOCKOPT,
+            fd,
+            level,
+            name,
+            &val as *const u32 as usize,
+            core::mem::size_of::<u32>(),
+        )
+    })
+    .map(|_| ())
+}
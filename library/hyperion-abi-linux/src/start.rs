@@ -0,0 +1,304 @@
+//! The ELF entry point, thread-local storage and thread control blocks.
+//!
+//! Linux starts the process with argc, argv, envp and the aux vector on the
+//! stack, and without any thread-local storage. The entry point relocates the
+//! static-PIE binary, sets up TLS for the main thread, converts the arguments
+//! and environment into the format the Hyperion kernel passes to `_start`, and
+//! then calls std's `_start`.
+
+use core::arch::{asm, global_asm};
+use core::sync::atomic::{AtomicUsize, Ordering};
+use core::{mem, ptr};
+
+use crate::linux::*;
+use crate::sys::err::Result;
+
+//
+
+extern "C" {
+    /// The Hyperion entry point in std's PAL.
+    fn _start(hyperion_cli_args_ptr: usize, hyperion_env_ptr: usize) -> !;
+
+    /// Applies the binary's relocations, std's `_start` leaves that to the
+    /// shim on this target.
+    fn __hyperion_linux_relocate();
+}
+
+global_asm!(
+    ".globl __hyperion_linux_start",
+    ".type __hyperion_linux_start, @function",
+    "__hyperion_linux_start:",
+    "xor ebp, ebp",
+    "mov rdi, rsp",
+    "and rsp, -16",
+    "call {start}",
+    "ud2",
+    start = sym linux_start,
+);
+
+/// # Safety
+///
+/// `sp` has to point to the stack Linux starts the process with.
+unsafe extern "C" fn linux_start(sp: *const usize) -> ! {
+    const AT_NULL: usize = 0;
+    const AT_PHDR: usize = 3;
+    const AT_PHNUM: usize = 5;
+
+    // the TLS image copied below may contain pointers
+    // SAFETY: nothing has read a pointer from the binary's data yet
+    unsafe { __hyperion_linux_relocate() };
+
+    // SAFETY: Linux lays out argc, argv, envp and auxv like this
+    unsafe {
+        let argc = *sp;
+        let argv = sp.add(1) as *const *const u8;
+        let envp = argv.add(argc + 1);
+        let mut envc = 0;
+        while !(*envp.add(envc)).is_null() {
+            envc += 1;
+        }
+
+        let mut auxv = envp.add(envc + 1) as *const [usize; 2];
+        let (mut phdr, mut phnum) = (0, 0);
+        while (*auxv)[0] != AT_NULL {
+            match (*auxv)[0] {
+                AT_PHDR => phdr = (*auxv)[1],
+                AT_PHNUM => phnum = (*auxv)[1],
+                _ => {}
+            }
+            auxv = auxv.add(1);
+        }
+
+        TLS_IMAGE.init(phdr, phnum);
+        if init_main_thread().is_err() {
+            fatal();
+        }
+
+        let (Ok(args), Ok(env)) = (pack_strings(argv, argc), pack_strings(envp, envc)) else {
+            fatal();
+        };
+        _start(args, env)
+    }
+}
+
+/// Copies `n` C strings into a new mapping: the count, then all lengths and
+/// then all bytes.
+///
+/// # Safety
+///
+/// `strings` has to point to `n` valid C strings.
+unsafe fn pack_strings(strings: *const *const u8, n: usize) -> Result<usize> {
+    // SAFETY: the caller guarantees that the strings are valid
+    let string = |i: usize| unsafe {
+        let s = *strings.add(i);
+        let mut len = 0;
+        while *s.add(len) != 0 {
+            len += 1;
+        }
+        (s, len)
+    };
+
+    let bytes: usize = (0..n).map(|i| string(i).1).sum();
+    let head = (n + 1) * mem::size_of::<usize>();
+    let buf = mmap(head + bytes)?;
+
+    // SAFETY: the mapping is big enough for the whole layout
+    unsafe {
+        let lens = buf as *mut usize;
+        *lens = n;
+        let mut data = (buf + head) as *mut u8;
+        for i in 0..n {
+            let (s, len) = string(i);
+            *lens.add(i + 1) = len;
+            ptr::copy_nonoverlapping(s, data, len);
+            data = data.add(len);
+        }
+    }
+
+    Ok(buf)
+}
+
+/// Exits without touching std, which isn't initialized yet.
+fn fatal() -> ! {
+    // SAFETY: the process exits
+    unsafe { syscall1(EXIT_GROUP, 127) };
+    unreachable!()
+}
+
+//
+
+/// Per-thread state, located at the thread pointer right after the thread's
+/// TLS block. x86_64 requires `fs:0` to hold the thread pointer itself.
+#[repr(C)]
+pub(crate) struct Tcb {
+    self_ptr: usize,
+    /// the mapping with the thread's stack, TLS and TCB, `0` on the main thread
+    pub map_base: usize,
+    pub map_len: usize,
+    pub stack_top: usize,
+    /// the unmapped page below the stack, `0..0` if unknown
+    pub guard_start: usize,
+    pub guard_end: usize,
+    pub alt_stack: usize,
+}
+
+/// Returns the calling thread's TCB.
+pub(crate) fn current() -> &'static Tcb {
+    let tp: usize;
+    // SAFETY: every thread gets a TCB before it runs any other code
+    unsafe {
+        asm!("mov {}, fs:0", out(reg) tp, options(nostack, readonly, preserves_flags));
+        &*(tp as *const Tcb)
+    }
+}
+
+/// The `PT_TLS` segment every thread's TLS block is initialized from.
+struct TlsImage {
+    addr: AtomicUsize,
+    file_len: AtomicUsize,
+    mem_len: AtomicUsize,
+    align: AtomicUsize,
+}
+
+static TLS_IMAGE: TlsImage = TlsImage {
+    addr: AtomicUsize::new(0),
+    file_len: AtomicUsize::new(0),
+    mem_len: AtomicUsize::new(0),
+    align: AtomicUsize::new(1),
+};
+
+impl TlsImage {
+    /// # Safety
+    ///
+    /// `phdr` has to point to the binary's `phnum` program headers.
+    unsafe fn init(&self, phdr: usize, phnum: usize) {
+        const PT_PHDR: u32 = 6;
+        const PT_TLS: u32 = 7;
+
+        // `Elf64_Phdr`, p_type at 0, p_vaddr at 16, p_filesz at 32,
+        // p_memsz at 40 and p_align at 48
+        let field = |i: usize, offset: usize| -> usize {
+            // SAFETY: the caller guarantees that the program headers are mapped
+            unsafe { *((phdr + i * 56 + offset) as *const usize) }
+        };
+        let ty = |i: usize| field(i, 0) as u32;
+
+        // the headers are mapped at their vaddr plus the load bias
+        let bias = (0..phnum).find(|&i| ty(i) == PT_PHDR).map_or(0, |i| phdr - field(i, 16));
+
+        if let Some(i) = (0..phnum).find(|&i| ty(i) == PT_TLS) {
+            self.addr.store(bias + field(i, 16), Ordering::Relaxed);
+            self.file_len.store(field(i, 32), Ordering::Relaxed);
+            self.mem_len.store(field(i, 40), Ordering::Relaxed);
+            self.align.store(field(i, 48).max(1), Ordering::Relaxed);
+        }
+    }
+
+    fn align(&self) -> usize {
+        self.align.load(Ordering::Relaxed).max(mem::align_of::<Tcb>())
+    }
+
+    /// The space from the start of a thread's TLS block up to its thread pointer.
+    fn tls_len(&self) -> usize {
+        self.mem_len.load(Ordering::Relaxed).next_multiple_of(self.align())
+    }
+
+    /// The size of a block that fits the TLS data and the TCB.
+    fn block_len(&self) -> usize {
+        self.tls_len() + self.align() + mem::size_of::<Tcb>()
+    }
+
+    /// Initializes a TLS block and its TCB, returns the thread pointer.
+    ///
+    /// # Safety
+    ///
+    /// `block` has to be writable for `block_len()` bytes.
+    unsafe fn init_block(&self, block: usize) -> usize {
+        let tp = (block + self.tls_len()).next_multiple_of(self.align());
+        let data = tp - self.tls_len();
+        let file_len = self.file_len.load(Ordering::Relaxed);
+
+        // SAFETY: the caller guarantees that the block is writable, the
+        // image is part of the binary
+        unsafe {
+            // the binary might not have any TLS at all
+            if file_len != 0 {
+                ptr::copy_nonoverlapping(
+                    self.addr.load(Ordering::Relaxed) as *const u8,
+                    data as *mut u8,
+                    file_len,
+                );
+            }
+            ptr::write_bytes((data + file_len) as *mut u8, 0, self.tls_len() - file_len);
+            (tp as *mut Tcb).write(Tcb {
+                self_ptr: tp,
+                map_base: 0,
+                map_len: 0,
+                stack_top: 0,
+                guard_start: 0,
+                guard_end: 0,
+                alt_stack: 0,
+            });
+        }
+
+        tp
+    }
+}
+
+/// Size of the stack the fault handler runs on.
+pub(crate) const ALT_STACK_LEN: usize = 0x10000;
+
+/// Gives the main thread its TLS block and a stack for the fault handler,
+/// the kernel already mapped its stack.
+fn init_main_thread() -> Result<()> {
+    const ARCH_SET_FS: usize = 0x1002;
+
+    let block = mmap(TLS_IMAGE.block_len())?;
+    let alt_stack = mmap(ALT_STACK_LEN)?;
+
+    // SAFETY: the block was just mapped with the right size
+    let tp = unsafe { TLS_IMAGE.init_block(block) };
+    // SAFETY: nothing has used TLS yet
+    check(unsafe { syscall2(ARCH_PRCTL, ARCH_SET_FS, tp) })?;
+    // SAFETY: `tp` points to the TCB written by `init_block`
+    unsafe { (*(tp as *mut Tcb)).alt_stack = alt_stack };
+
+    crate::sys::set_alt_stack(alt_stack);
+    Ok(())
+}
+
+/// Maps a new thread's guard page, stack, fault handler stack, TLS block and
+/// TCB in one mapping, so the thread can unmap all of it when it exits.
+///
+/// Returns the new thread pointer.
+pub(crate) fn new_thread(stack_len: usize) -> Result<usize> {
+    let stack_len = stack_len.next_multiple_of(PAGE_SIZE);
+    let block_len = TLS_IMAGE.block_len().next_multiple_of(PAGE_SIZE);
+    let map_len = PAGE_SIZE + stack_len + ALT_STACK_LEN + block_len;
+
+    let map_base = mmap(map_len)?;
+    // SAFETY: the guard page is part of the new mapping
+    if let Err(err) = check(unsafe { syscall3(MPROTECT, map_base, PAGE_SIZE, PROT_NONE) }) {
+        // SAFETY: nothing uses the mapping yet
+        _ = unsafe { munmap(map_base, map_len) };
+        return Err(err);
+    }
+
+    let stack_top = map_base + PAGE_SIZE + stack_len;
+    let alt_stack = stack_top;
+
+    // SAFETY: the block is the last part of the new mapping
+    let tp = unsafe { TLS_IMAGE.init_block(alt_stack + ALT_STACK_LEN) };
+    // SAFETY: `tp` points to the TCB written by `init_block`
+    unsafe {
+        let tcb = &mut *(tp as *mut Tcb);
+        tcb.map_base = map_base;
+        tcb.map_len = map_len;
+        tcb.stack_top = stack_top;
+        tcb.guard_start = map_base;
+        tcb.guard_end = map_base + PAGE_SIZE;
+        tcb.alt_stack = alt_stack;
+    }
+
+    Ok(tp)
+}
@@ -0,0 +1,114 @@
+use core::fmt;
+
+//
+
+pub type Result<T = (), E = Error> = core::result::Result<T, E>;
+
+/// A Hyperion error code.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Error(pub usize);
+
+macro_rules! errors {
+    ($($name:ident = $code:literal => $msg:literal,)*) => {
+        impl Error {
+            $(pub const $name: Error = Error($code);)*
+
+            pub fn as_str(self) -> &'static str {
+                match self {
+                    $(Error::$name => $msg,)*
+                    _ => "unknown error",
+                }
+            }
+        }
+    };
+}
+
+errors! {
+    UNKNOWN = 1 => "unknown error",
+    ADDR_IN_USE = 2 => "address in use",
+    ADDR_NOT_AVAILABLE = 3 => "address not available",
+    ALREADY_EXISTS = 4 => "already exists",
+    BAD_FILE_DESCRIPTOR = 5 => "bad file descriptor",
+    CLOSED = 6 => "closed",
+    CONNECTION_ABORTED = 7 => "connection aborted",
+    CONNECTION_REFUSED = 8 => "connection refused",
+    CONNECTION_RESET = 9 => "connection reset",
+    CROSSES_DEVICES = 10 => "crosses devices",
+    DIRECTORY_NOT_EMPTY = 11 => "directory not empty",
+    FILESYSTEM_ERROR = 12 => "filesystem error",
+    INTERRUPTED = 13 => "interrupted",
+    INVALID_ADDRESS = 14 => "invalid address",
+    INVALID_ALLOC = 15 => "invalid alloc",
+    INVALID_ARGUMENT = 16 => "invalid argument",
+    INVALID_DOMAIN = 17 => "invalid domain",
+    INVALID_FLAGS = 18 => "invalid flags",
+    INVALID_TYPE = 19 => "invalid type",
+    INVALID_UTF8 = 20 => "invalid utf8",
+    IS_A_PIPE = 21 => "is a pipe",
+    NOT_A_DIRECTORY = 22 => "not a directory",
+    NOT_A_FILE = 23 => "not a file",
+    NOT_A_SOCKET = 24 => "not a socket",
+    NOT_A_SYMLINK = 25 => "not a symlink",
+    NOT_CONNECTED = 26 => "not connected",
+    NOT_FOUND = 27 => "not found",
+    NO_SUCH_PROCESS = 28 => "no such process",
+    OUT_OF_MEMORY = 29 => "out of memory",
+    OUT_OF_VIRTUAL_MEMORY = 30 => "out of virtual memory",
+    PERMISSION_DENIED = 31 => "permission denied",
+    TIMED_OUT = 32 => "timed out",
+    UNEXPECTED_EOF = 33 => "unexpected eof",
+    UNKNOWN_PROTOCOL = 34 => "unknown protocol",
+    WRITE_ZERO = 35 => "write zero",
+    WOULD_BLOCK = 36 => "operation would block",
+    UNSUPPORTED = 37 => "operation not supported",
+}
+
+impl Error {
+    /// Translates a Linux errno into the error the Hyperion kernel would return.
+    pub(crate) fn from_errno(errno: usize) -> Error {
+        match errno {
+            1 | 13 => Error::PERMISSION_DENIED, // EPERM, EACCES
+            2 => Error::NOT_FOUND,              // ENOENT
+            3 | 10 => Error::NO_SUCH_PROCESS,   // ESRCH, ECHILD
+            4 => Error::INTERRUPTED,            // EINTR
+            5 | 40 => Error::FILESYSTEM_ERROR,  // EIO, ELOOP
+            9 => Error::BAD_FILE_DESCRIPTOR,    // EBADF
+            // socket timeouts show up as EAGAIN as well
+            11 => Error::WOULD_BLOCK,                // EAGAIN
+            110 | 115 => Error::TIMED_OUT,           // ETIMEDOUT, EINPROGRESS
+            12 => Error::OUT_OF_MEMORY,              // ENOMEM
+            14 => Error::INVALID_ADDRESS,            // EFAULT
+            17 => Error::ALREADY_EXISTS,             // EEXIST
+            18 => Error::CROSSES_DEVICES,            // EXDEV
+            20 => Error::NOT_A_DIRECTORY,            // ENOTDIR
+            21 => Error::NOT_A_FILE,                 // EISDIR
+            22 | 25 | 36 => Error::INVALID_ARGUMENT, // EINVAL, ENOTTY, ENAMETOOLONG
+            29 => Error::IS_A_PIPE,                  // ESPIPE
+            32 => Error::CLOSED,                     // EPIPE
+            38 | 95 => Error::UNSUPPORTED,           // ENOSYS, EOPNOTSUPP
+            39 => Error::DIRECTORY_NOT_EMPTY,        // ENOTEMPTY
+            88 => Error::NOT_A_SOCKET,               // ENOTSOCK
+            93 => Error::UNKNOWN_PROTOCOL,           // EPROTONOSUPPORT
+            97 => Error::INVALID_DOMAIN,             // EAFNOSUPPORT
+            98 => Error::ADDR_IN_USE,                // EADDRINUSE
+            99 => Error::ADDR_NOT_AVAILABLE,         // EADDRNOTAVAIL
+            103 => Error::CONNECTION_ABORTED,        // ECONNABORTED
+            104 => Error::CONNECTION_RESET,          // ECONNRESET
+            107 => Error::NOT_CONNECTED,             // ENOTCONN
+            111 => Error::CONNECTION_REFUSED,        // ECONNREFUSED
+            _ => Error::UNKNOWN,
+        }
+    }
+}
+
+impl fmt::Debug for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Error({}: {})", self.0, self.as_str())
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
@@ -0,0 +1,430 @@
+use core::mem::MaybeUninit;
+
+use super::err::{Error, Result};
+use super::fs::{
+    DIR_ENTRY_NAME_LEN, DirEntry, FileDesc, FileKind, FileOpenFlags, Metadata, PollEvents, PollFd,
+};
+use crate::linux::*;
+
+// All syscalls below only get pointers into live buffers that are valid for
+// the lengths passed along with them.
+
+//
+
+/// Opens a file or a directory, every descriptor is closed on exec.
+///
+/// Directories are only opened with [`FileOpenFlags::IS_DIR`], and created
+/// if [`FileOpenFlags::CREATE`] or [`FileOpenFlags::CREATE_NEW`] is set.
+pub fn open(path: &str, flags: FileOpenFlags, _mode: usize) -> Result<FileDesc> {
+    if flags.contains(FileOpenFlags::CREATE_DIRS) {
+        create_parents(path)?;
+    }
+
+    let is_dir = flags.contains(FileOpenFlags::IS_DIR);
+    let create = flags.intersection(FileOpenFlags::CREATE | FileOpenFlags::CREATE_NEW);
+    if is_dir && !create.is_empty() {
+        match mkdir(path) {
+            Err(Error::ALREADY_EXISTS) if !flags.contains(FileOpenFlags::CREATE_NEW) => {}
+            Err(err) => return Err(err),
+            Ok(()) => {}
+        }
+    }
+
+    let mut oflags = O_CLOEXEC;
+    if flags.contains(FileOpenFlags::NO_FOLLOW) {
+        // only used to look at the link itself
+        oflags |= O_PATH | O_NOFOLLOW;
+    } else if is_dir {
+        // directories are always opened read only
+        oflags |= O_DIRECTORY;
+    } else {
+        oflags |= match (flags.contains(FileOpenFlags::READ), flags.contains(FileOpenFlags::WRITE))
+        {
+            (true, true) => O_RDWR,
+            (false, true) => O_WRONLY,
+            _ => O_RDONLY,
+        };
+        if flags.contains(FileOpenFlags::APPEND) {
+            oflags |= O_APPEND;
+        }
+        if flags.contains(FileOpenFlags::TRUNC) {
+            oflags |= O_TRUNC;
+        }
+        if flags.contains(FileOpenFlags::CREATE) {
+            oflags |= O_CREAT;
+        }
+        if flags.contains(FileOpenFlags::CREATE_NEW) {
+            oflags |= O_CREAT | O_EXCL;
+        }
+    }
+
+    let fd = with_cstr(path, |p| retry(|| unsafe { syscall4(OPENAT, AT_FDCWD, p, oflags, 0o666) }))
+        .map(FileDesc)?;
+
+    // Hyperion only opens directories as directories
+    if !is_dir {
+        let mut meta = Metadata::zeroed();
+        metadata(fd, &mut meta)?;
+        if meta.kind == FileKind::DIR {
+            _ = close(fd);
+            return Err(Error::NOT_A_FILE);
+        }
+    }
+
+    Ok(fd)
+}
+
+fn create_parents(path: &str) -> Result<()> {
+    let mut end = 0;
+    while let Some(next) = path[end + 1..].find('/') {
+        end += next + 1;
+        let parent = &path[..end];
+        if parent.is_empty() {
+            continue;
+        }
+        match mkdir(parent) {
+            Ok(()) | Err(Error::ALREADY_EXISTS) => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(())
+}
+
+fn mkdir(path: &str) -> Result<()> {
+    with_cstr(path, |p| check(unsafe { syscall3(MKDIRAT, AT_FDCWD, p, 0o777) })).map(|_| ())
+}
+
+pub fn close(fd: FileDesc) -> Result<()> {
+    check(unsafe { syscall1(CLOSE, fd.0) }).map(|_| ())
+}
+
+pub fn metadata(fd: FileDesc, meta: &mut Metadata) -> Result<()> {
+    let mut stat = Stat::default();
+    check(unsafe { syscall2(FSTAT, fd.0, &mut stat as *mut Stat as usize) })?;
+    *meta = to_metadata(&stat);
+    Ok(())
+}
+
+/// Like [`metadata`], but without opening the file, so it works on files the
+/// caller can't read. Symlinks are only followed if `follow` is set.
+pub fn metadata_path(path: &str, follow: bool, meta: &mut Metadata) -> Result<()> {
+    let mut stat = Stat::default();
+    let flags = if follow { 0 } else { AT_SYMLINK_NOFOLLOW };
+    with_cstr(path, |p| {
+        check(unsafe { syscall4(NEWFSTATAT, AT_FDCWD, p, &mut stat as *mut Stat as usize, flags) })
+    })?;
+    *meta = to_metadata(&stat);
+    Ok(())
+}
+
+fn to_metadata(stat: &Stat) -> Metadata {
+    Metadata {
+        len: stat.size as usize,
+        kind: FileKind::from_mode(stat.mode),
+        mode: stat.mode & 0o7777,
+        // Linux doesn't keep the creation time in `struct stat`
+        created: 0,
+        modified: stat.mtime.as_nanos(),
+        accessed: stat.atime.as_nanos(),
+    }
+}
+
+pub fn read(fd: FileDesc, buf: &mut [u8]) -> Result<usize> {
+    check(unsafe { syscall3(READ, fd.0, buf.as_mut_ptr() as usize, buf.len()) })
+}
+
+pub fn read_uninit(fd: FileDesc, buf: &mut [MaybeUninit<u8>]) -> Result<usize> {
+    check(unsafe { syscall3(READ, fd.0, buf.as_mut_ptr() as usize, buf.len()) })
+}
+
+pub fn write(fd: FileDesc, buf: &[u8]) -> Result<usize> {
+    check(unsafe { syscall3(WRITE, fd.0, buf.as_ptr() as usize, buf.len()) })
+}
+
+/// `struct iovec`
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct IoVec {
+    base: usize,
+    len: usize,
+}
+
+/// Buffers past this are left for the next call, like a short read or write.
+const MAX_IOVECS: usize = 64;
+
+pub fn readv(fd: FileDesc, bufs: &mut [&mut [u8]]) -> Result<usize> {
+    let mut iovecs = [IoVec { base: 0, len: 0 }; MAX_IOVECS];
+    let n = bufs.len().min(MAX_IOVECS);
+    for (iovec, buf) in iovecs.iter_mut().zip(bufs.iter_mut()) {
+        *iovec = IoVec { base: buf.as_mut_ptr() as usize, len: buf.len() };
+    }
+    check(unsafe { syscall3(READV, fd.0, iovecs.as_ptr() as usize, n) })
+}
+
+pub fn writev(fd: FileDesc, bufs: &[&[u8]]) -> Result<usize> {
+    let mut iovecs = [IoVec { base: 0, len: 0 }; MAX_IOVECS];
+    let n = bufs.len().min(MAX_IOVECS);
+    for (iovec, buf) in iovecs.iter_mut().zip(bufs.iter()) {
+        *iovec = IoVec { base: buf.as_ptr() as usize, len: buf.len() };
+    }
+    check(unsafe { syscall3(WRITEV, fd.0, iovecs.as_ptr() as usize, n) })
+}
+
+/// Returns the new position.
+pub fn seek(fd: FileDesc, offset: isize, origin: usize) -> Result<usize> {
+    check(unsafe { syscall3(LSEEK, fd.0, offset as usize, origin) })
+}
+
+/// Reads from `offset` without moving the position, fails with
+/// [`Error::IS_A_PIPE`] on descriptors that can't seek.
+pub fn pread(fd: FileDesc, buf: &mut [u8], offset: usize) -> Result<usize> {
+    check(unsafe { syscall4(PREAD64, fd.0, buf.as_mut_ptr() as usize, buf.len(), offset) })
+}
+
+/// Writes at `offset` without moving the position, fails with
+/// [`Error::IS_A_PIPE`] on descriptors that can't seek.
+pub fn pwrite(fd: FileDesc, buf: &[u8], offset: usize) -> Result<usize> {
+    check(unsafe { syscall4(PWRITE64, fd.0, buf.as_ptr() as usize, buf.len(), offset) })
+}
+
+pub fn fsync(fd: FileDesc) -> Result<()> {
+    check(unsafe { syscall1(FSYNC, fd.0) }).map(|_| ())
+}
+
+pub fn truncate(fd: FileDesc, len: usize) -> Result<()> {
+    check(unsafe { syscall2(FTRUNCATE, fd.0, len) }).map(|_| ())
+}
+
+pub fn dup(fd: FileDesc) -> Result<FileDesc> {
+    check(unsafe { syscall3(FCNTL, fd.0, F_DUPFD_CLOEXEC, 0) }).map(FileDesc)
+}
+
+pub fn set_mode(fd: FileDesc, mode: u32) -> Result<()> {
+    check(unsafe { syscall2(FCHMOD, fd.0, mode as usize) }).map(|_| ())
+}
+
+/// Sets the access and modification times, `None` leaves a time as is.
+pub fn set_times(fd: FileDesc, accessed: Option<u64>, modified: Option<u64>) -> Result<()> {
+    const UTIME_OMIT: i64 = (1 << 30) - 2;
+    let time = |nanos: Option<u64>| match nanos {
+        Some(nanos) => Timespec::from_nanos(nanos),
+        None => Timespec { sec: 0, nsec: UTIME_OMIT },
+    };
+    let times = [time(accessed), time(modified)];
+    check(unsafe { syscall4(UTIMENSAT, fd.0, 0, times.as_ptr() as usize, 0) }).map(|_| ())
+}
+
+/// Makes reads and writes on `fd` fail with [`Error::WOULD_BLOCK`] instead of
+/// blocking. The flag is shared by all duplicates of the descriptor.
+pub fn set_nonblocking(fd: FileDesc, nonblocking: bool) -> Result<()> {
+    let flags = check(unsafe { syscall2(FCNTL, fd.0, F_GETFL) })?;
+    let flags = if nonblocking { flags | O_NONBLOCK } else { flags & !O_NONBLOCK };
+    check(unsafe { syscall3(FCNTL, fd.0, F_SETFL, flags) }).map(|_| ())
+}
+
+/// `struct pollfd`
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct LinuxPollFd {
+    fd: i32,
+    events: i16,
+    revents: i16,
+}
+
+/// Waits until one of `fds` is ready or `timeout` nanoseconds passed, `None`
+/// waits forever. Returns the number of ready descriptors, `0` on a timeout.
+pub fn poll(fds: &mut [PollFd], timeout: Option<u64>) -> Result<usize> {
+    const POLLIN: i16 = 0x1;
+    const POLLOUT: i16 = 0x4;
+    const POLLERR: i16 = 0x8;
+    const POLLHUP: i16 = 0x10;
+    const POLLNVAL: i16 = 0x20;
+    /// Descriptors past this are converted in a separate mapping.
+    const ON_STACK: usize = 64;
+
+    let mut stack = [LinuxPollFd { fd: 0, events: 0, revents: 0 }; ON_STACK];
+    let len = fds.len() * core::mem::size_of::<LinuxPollFd>();
+    let map = if fds.len() > ON_STACK { Some(mmap(len)?) } else { None };
+    // SAFETY: the mapping is big enough for all descriptors and zeroed
+    let linux = match map {
+        Some(map) => unsafe { core::slice::from_raw_parts_mut(map as *mut LinuxPollFd, fds.len()) },
+        None => &mut stack[..fds.len()],
+    };
+
+    for (linux, fd) in linux.iter_mut().zip(fds.iter()) {
+        let mut events = 0;
+        if fd.events.contains(PollEvents::READ) {
+            events |= POLLIN;
+        }
+        if fd.events.contains(PollEvents::WRITE) {
+            events |= POLLOUT;
+        }
+        *linux = LinuxPollFd { fd: fd.fd.0 as i32, events, revents: 0 };
+    }
+
+    let timeout = timeout.map(Timespec::from_nanos);
+    let timeout = timeout.as_ref().map_or(0, |timeout| timeout as *const Timespec as usize);
+    let ret =
+        check(unsafe { syscall5(PPOLL, linux.as_mut_ptr() as usize, linux.len(), timeout, 0, 8) });
+
+    for (linux, fd) in linux.iter().zip(fds.iter_mut()) {
+        let mut revents = PollEvents::empty();
+        if linux.revents & POLLIN != 0 {
+            revents |= PollEvents::READ;
+        }
+        if linux.revents & POLLOUT != 0 {
+            revents |= PollEvents::WRITE;
+        }
+        if linux.revents & (POLLERR | POLLNVAL) != 0 {
+            revents |= PollEvents::ERROR;
+        }
+        if linux.revents & POLLHUP != 0 {
+            revents |= PollEvents::HANGUP;
+        }
+        fd.revents = revents;
+    }
+
+    if let Some(map) = map {
+        // SAFETY: nothing uses the mapping anymore
+        _ = unsafe { munmap(map, len) };
+    }
+    ret
+}
+
+pub fn isatty(fd: FileDesc) -> Result<bool> {
+    const TCGETS: usize = 0x5401;
+    // `struct termios`
+    let mut termios = [0u8; 60];
+    let ret = unsafe { syscall3(IOCTL, fd.0, TCGETS, termios.as_mut_ptr() as usize) };
+    if ret == ENOTTY.wrapping_neg() {
+        return Ok(false);
+    }
+    check(ret).map(|_| true)
+}
+
+/// Reads the next entry of an open directory, returns `false` at the end.
+pub fn read_dir(fd: FileDesc, entry: &mut DirEntry) -> Result<bool> {
+    // `struct linux_dirent64`: d_ino, d_off, d_reclen, d_type and then the name
+    const OFF: usize = 8;
+    const TYPE: usize = 18;
+    const NAME: usize = 19;
+    const DT_DIR: u8 = 4;
+    const DT_LNK: u8 = 10;
+    const DT_UNKNOWN: u8 = 0;
+
+    let mut buf = [0u8; 512];
+    let n = check(unsafe { syscall3(GETDENTS64, fd.0, buf.as_mut_ptr() as usize, buf.len()) })?;
+    if n == 0 {
+        return Ok(false);
+    }
+
+    let off = i64::from_ne_bytes(buf[OFF..OFF + 8].try_into().unwrap());
+    let name = &buf[NAME..];
+    let name = &name[..name.iter().position(|&b| b == 0).unwrap_or(name.len())];
+
+    // getdents returns as many entries as fit, continue right after the first one
+    check(unsafe { syscall3(LSEEK, fd.0, off as usize, 0) })?;
+
+    let len = name.len().min(entry.name.len());
+    entry.name[..len].copy_from_slice(&name[..len]);
+    entry.name_len = len;
+    entry.kind = match buf[TYPE] {
+        DT_DIR => FileKind::DIR,
+        DT_LNK => FileKind::SYMLINK,
+        DT_UNKNOWN => {
+            let mut stat = Stat::default();
+            let mut path = [0u8; DIR_ENTRY_NAME_LEN + 1];
+            path[..len].copy_from_slice(&name[..len]);
+            check(unsafe {
+                syscall4(
+                    NEWFSTATAT,
+                    fd.0,
+                    path.as_ptr() as usize,
+                    &mut stat as *mut Stat as usize,
+                    AT_SYMLINK_NOFOLLOW,
+                )
+            })?;
+            FileKind::from_mode(stat.mode)
+        }
+        _ => FileKind::FILE,
+    };
+
+    Ok(true)
+}
+
+//
+
+pub fn unlink(path: &str) -> Result<()> {
+    with_cstr(path, |p| check(unsafe { syscall3(UNLINKAT, AT_FDCWD, p, 0) })).map(|_| ())
+}
+
+pub fn rmdir(path: &str) -> Result<()> {
+    with_cstr(path, |p| check(unsafe { syscall3(UNLINKAT, AT_FDCWD, p, AT_REMOVEDIR) })).map(|_| ())
+}
+
+/// Like [`set_mode`], but doesn't need a descriptor that can read the file.
+pub fn set_mode_path(path: &str, mode: u32) -> Result<()> {
+    with_cstr(path, |p| check(unsafe { syscall3(FCHMODAT, AT_FDCWD, p, mode as usize) }))
+        .map(|_| ())
+}
+
+pub fn rename_path(from: &str, to: &str) -> Result<()> {
+    with_cstr(from, |from| {
+        with_cstr(to, |to| check(unsafe { syscall4(RENAMEAT, AT_FDCWD, from, AT_FDCWD, to) }))
+    })
+    .map(|_| ())
+}
+
+pub fn link(original: &str, link: &str) -> Result<()> {
+    with_cstr(original, |original| {
+        with_cstr(link, |link| {
+            check(unsafe { syscall5(LINKAT, AT_FDCWD, original, AT_FDCWD, link, 0) })
+        })
+    })
+    .map(|_| ())
+}
+
+pub fn symlink(original: &str, link: &str) -> Result<()> {
+    with_cstr(original, |original| {
+        with_cstr(link, |link| check(unsafe { syscall3(SYMLINKAT, original, AT_FDCWD, link) }))
+    })
+    .map(|_| ())
+}
+
+/// Returns the length of the link target, the target is truncated if it
+/// doesn't fit into `buf`.
+pub fn readlink(path: &str, buf: &mut [u8]) -> Result<usize> {
+    with_cstr(path, |p| {
+        check(unsafe { syscall4(READLINKAT, AT_FDCWD, p, buf.as_mut_ptr() as usize, buf.len()) })
+    })
+}
+
+pub fn pipe() -> Result<[FileDesc; 2]> {
+    let mut fds = [0u32; 2];
+    check(unsafe { syscall2(PIPE2, fds.as_mut_ptr() as usize, O_CLOEXEC) })?;
+    Ok([FileDesc(fds[0] as usize), FileDesc(fds[1] as usize)])
+}
+
+//
+
+/// Returns the length of the path, the path is truncated if it doesn't fit
+/// into `buf`.
+pub fn get_cwd(buf: &mut [u8]) -> Result<usize> {
+    let ret = unsafe { syscall2(GETCWD, buf.as_mut_ptr() as usize, buf.len()) };
+    if ret == ERANGE.wrapping_neg() {
+        // the caller retries with a bigger buffer
+        return Ok(buf.len());
+    }
+    // the length includes the NUL
+    check(ret).map(|len| len - 1)
+}
+
+pub fn chdir(path: &str) -> Result<()> {
+    with_cstr(path, |p| check(unsafe { syscall1(CHDIR, p) })).map(|_| ())
+}
+
+/// Returns the length of the path, the path is truncated if it doesn't fit
+/// into `buf`.
+pub fn exe_path(buf: &mut [u8]) -> Result<usize> {
+    readlink("/proc/self/exe", buf)
+}
@@ -0,0 +1,204 @@
+use core::{fmt, ops, str};
+
+//
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct FileDesc(pub usize);
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FileOpenFlags(u32);
+
+impl FileOpenFlags {
+    pub const READ: Self = Self(1 << 0);
+    pub const WRITE: Self = Self(1 << 1);
+    pub const READ_WRITE: Self = Self(Self::READ.0 | Self::WRITE.0);
+    pub const APPEND: Self = Self(1 << 2);
+    pub const CREATE: Self = Self(1 << 3);
+    pub const CREATE_NEW: Self = Self(1 << 4);
+    pub const TRUNC: Self = Self(1 << 5);
+    pub const IS_DIR: Self = Self(1 << 6);
+    pub const CREATE_DIRS: Self = Self(1 << 7);
+    pub const NO_FOLLOW: Self = Self(1 << 8);
+
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    pub const fn bits(self) -> u32 {
+        self.0
+    }
+
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub const fn intersection(self, other: Self) -> Self {
+        Self(self.0 & other.0)
+    }
+
+    pub fn set(&mut self, other: Self, value: bool) {
+        if value {
+            self.0 |= other.0;
+        } else {
+            self.0 &= !other.0;
+        }
+    }
+}
+
+impl ops::BitOr for FileOpenFlags {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl ops::BitOrAssign for FileOpenFlags {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+impl fmt::Debug for FileOpenFlags {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "FileOpenFlags({:#x})", self.0)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FileKind(pub u8);
+
+impl FileKind {
+    pub const FILE: Self = Self(0);
+    pub const DIR: Self = Self(1);
+    pub const SYMLINK: Self = Self(2);
+
+    pub(crate) fn from_mode(mode: u32) -> Self {
+        match mode & crate::linux::S_IFMT {
+            crate::linux::S_IFDIR => Self::DIR,
+            crate::linux::S_IFLNK => Self::SYMLINK,
+            _ => Self::FILE,
+        }
+    }
+}
+
+/// File metadata, timestamps are nanoseconds since the unix epoch and `0`
+/// if the filesystem doesn't keep track of them.
+#[derive(Debug, Clone, Copy)]
+pub struct Metadata {
+    pub len: usize,
+    pub kind: FileKind,
+    pub mode: u32,
+    pub created: u64,
+    pub modified: u64,
+    pub accessed: u64,
+}
+
+impl Metadata {
+    pub const fn zeroed() -> Self {
+        Self { len: 0, kind: FileKind::FILE, mode: 0, created: 0, modified: 0, accessed: 0 }
+    }
+}
+
+pub const DIR_ENTRY_NAME_LEN: usize = 256;
+
+/// One entry of a directory listing.
+#[derive(Clone, Copy)]
+pub struct DirEntry {
+    pub kind: FileKind,
+    pub name_len: usize,
+    pub name: [u8; DIR_ENTRY_NAME_LEN],
+}
+
+impl DirEntry {
+    pub const fn zeroed() -> Self {
+        Self { kind: FileKind::FILE, name_len: 0, name: [0; DIR_ENTRY_NAME_LEN] }
+    }
+
+    pub fn name(&self) -> &str {
+        str::from_utf8(&self.name[..self.name_len]).unwrap_or("")
+    }
+}
+
+impl fmt::Debug for DirEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DirEntry").field("kind", &self.kind).field("name", &self.name()).finish()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Seek(pub usize);
+
+impl Seek {
+    pub const SET: Self = Self(0);
+    pub const CUR: Self = Self(1);
+    pub const END: Self = Self(2);
+}
+
+/// What [`poll`](super::poll) waits for on a descriptor, and what it found.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PollEvents(u16);
+
+impl PollEvents {
+    /// Reading won't block.
+    pub const READ: Self = Self(1 << 0);
+    /// Writing won't block.
+    pub const WRITE: Self = Self(1 << 1);
+    /// Only reported, the descriptor has an error pending.
+    pub const ERROR: Self = Self(1 << 2);
+    /// Only reported, the other end was closed.
+    pub const HANGUP: Self = Self(1 << 3);
+
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    pub const fn bits(self) -> u16 {
+        self.0
+    }
+
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub const fn intersects(self, other: Self) -> bool {
+        self.0 & other.0 != 0
+    }
+}
+
+impl ops::BitOr for PollEvents {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl ops::BitOrAssign for PollEvents {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+impl fmt::Debug for PollEvents {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "PollEvents({:#x})", self.0)
+    }
+}
+
+/// One descriptor passed to [`poll`](super::poll).
+#[derive(Debug, Clone, Copy)]
+pub struct PollFd {
+    pub fd: FileDesc,
+    pub events: PollEvents,
+    /// filled in by `poll`
+    pub revents: PollEvents,
+}
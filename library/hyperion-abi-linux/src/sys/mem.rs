@@ -0,0 +1,40 @@
+use core::ptr::NonNull;
+
+use super::err::{Error, Result};
+use crate::linux::*;
+
+//
+
+/// Maps `pages` fresh zeroed pages.
+pub fn palloc(pages: usize) -> Result<Option<NonNull<u8>>> {
+    if pages == 0 {
+        return Ok(None);
+    }
+    let len = pages.checked_mul(PAGE_SIZE).ok_or(Error::OUT_OF_VIRTUAL_MEMORY)?;
+    mmap(len).map(|addr| NonNull::new(addr as *mut u8))
+}
+
+/// Unmaps pages allocated with [`palloc`].
+pub fn pfree(alloc: NonNull<u8>, pages: usize) -> Result<()> {
+    // SAFETY: the caller gives up the pages
+    unsafe { munmap(alloc.as_ptr() as usize, pages * PAGE_SIZE) }
+}
+
+/// Grows or shrinks an allocation, moving it if it can't grow in place.
+pub fn prealloc(
+    alloc: NonNull<u8>,
+    old_pages: usize,
+    new_pages: usize,
+) -> Result<Option<NonNull<u8>>> {
+    // SAFETY: the caller owns the old pages
+    let addr = check(unsafe {
+        syscall4(
+            MREMAP,
+            alloc.as_ptr() as usize,
+            old_pages * PAGE_SIZE,
+            new_pages * PAGE_SIZE,
+            MREMAP_MAYMOVE,
+        )
+    })?;
+    Ok(NonNull::new(addr as *mut u8))
+}
@@ -0,0 +1,42 @@
+//! The syscalls, with the same names and signatures as in `hyperion-abi`.
+
+use crate::linux::*;
+
+pub mod err;
+pub mod fs;
+pub mod net;
+
+mod file;
+mod mem;
+mod process;
+mod socket;
+mod thread;
+
+use self::err::Result;
+pub use self::file::*;
+pub use self::mem::*;
+pub use self::process::*;
+pub use self::socket::*;
+pub(crate) use self::thread::set_alt_stack;
+pub use self::thread::{
+    cpu_count, done, futex_wait, futex_wait_until, futex_wake, nanosleep, rename,
+    set_fault_handler, spawn_with_stack, stack_guard, yield_now,
+};
+
+//
+
+/// Nanoseconds since the Unix epoch.
+pub fn system_time() -> Result<u64> {
+    clock_gettime(CLOCK_REALTIME)
+}
+
+/// Nanoseconds of a monotonic clock.
+pub fn timestamp() -> Result<u64> {
+    clock_gettime(CLOCK_MONOTONIC)
+}
+
+/// Fills `buf` with random bytes, returns how many were written.
+pub fn get_random(buf: &mut [u8]) -> Result<usize> {
+    // SAFETY: `buf` is valid for writes
+    retry(|| unsafe { syscall3(GETRANDOM, buf.as_mut_ptr() as usize, buf.len(), 0) })
+}
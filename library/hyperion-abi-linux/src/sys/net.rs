@@ -0,0 +1,66 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SocketDomain(pub usize);
+
+impl SocketDomain {
+    pub const LOCAL: Self = Self(1);
+    pub const INET: Self = Self(2);
+    pub const INET6: Self = Self(10);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SocketType(pub usize);
+
+impl SocketType {
+    pub const STREAM: Self = Self(1);
+    pub const DGRAM: Self = Self(2);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Protocol(pub usize);
+
+impl Protocol {
+    pub const LOCAL: Self = Self(0);
+    pub const TCP: Self = Self(6);
+    pub const UDP: Self = Self(17);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RecvFlags(usize);
+
+impl RecvFlags {
+    pub const PEEK: Self = Self(1 << 0);
+
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    pub const fn bits(self) -> usize {
+        self.0
+    }
+}
+
+/// Socket options, timeouts are in nanoseconds and `0` means no timeout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SockOpt(pub usize);
+
+impl SockOpt {
+    pub const READ_TIMEOUT: Self = Self(0);
+    pub const WRITE_TIMEOUT: Self = Self(1);
+    /// Stays on the socket after `connect`, like the other timeouts.
+    pub const CONNECT_TIMEOUT: Self = Self(2);
+    pub const NODELAY: Self = Self(3);
+    pub const TTL: Self = Self(4);
+    pub const ONLY_V6: Self = Self(5);
+    pub const BROADCAST: Self = Self(6);
+    pub const NONBLOCKING: Self = Self(7);
+    pub const ERROR: Self = Self(8);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Shutdown(pub usize);
+
+impl Shutdown {
+    pub const READ: Self = Self(0);
+    pub const WRITE: Self = Self(1);
+    pub const BOTH: Self = Self(2);
+}
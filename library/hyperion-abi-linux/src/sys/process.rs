@@ -0,0 +1,258 @@
+use core::{mem, ptr};
+
+use super::err::{Error, Result};
+use super::file::{close, pipe, read, write};
+use super::fs::FileDesc;
+use crate::linux::*;
+
+//
+
+/// How [`system_with`] starts a child.
+#[derive(Debug, Clone, Copy)]
+pub struct LaunchConfig<'a> {
+    pub stdin: FileDesc,
+    pub stdout: FileDesc,
+    pub stderr: FileDesc,
+    /// the working directory, the parent's if `None`
+    pub cwd: Option<&'a str>,
+    /// `KEY=VALUE` pairs, the child gets exactly these
+    pub env: &'a [&'a str],
+}
+
+pub fn exit(code: i64) -> ! {
+    // SAFETY: the process exits
+    unsafe { syscall1(EXIT_GROUP, code as usize) };
+    unreachable!()
+}
+
+pub fn get_pid() -> usize {
+    // SAFETY: getpid has no arguments
+    unsafe { syscall0(GETPID) }
+}
+
+/// Starts `program` with `args`, returns its pid.
+///
+/// A program name without a `/` is looked up in the child's `PATH`. The
+/// child only inherits the three stdio descriptors of `config`.
+pub fn system_with(program: &str, args: &[&str], config: LaunchConfig<'_>) -> Result<usize> {
+    let mut path = [0u8; PATH_MAX];
+    let path_len = resolve(program, config.env, &mut path)?;
+    let path = &path[..path_len];
+
+    // the child can't allocate, so everything `execve` needs is laid out first
+    let exec = ExecArgs::new(path, program, args, config.env, config.cwd)?;
+
+    // the child reports a failed `execve` through this pipe, which is closed
+    // on a successful one
+    let [err_r, err_w] = pipe()?;
+
+    // SAFETY: the child only makes syscalls until it execs or exits
+    let pid = match check(unsafe { syscall5(CLONE, SIGCHLD, 0, 0, 0, 0) }) {
+        Ok(0) => unsafe { exec.exec(&config, err_w) },
+        Ok(pid) => pid,
+        Err(err) => {
+            _ = close(err_r);
+            _ = close(err_w);
+            return Err(err);
+        }
+    };
+    _ = close(err_w);
+
+    let mut errno = [0u8; 4];
+    let mut n = 0;
+    while n < errno.len() {
+        match read(err_r, &mut errno[n..]) {
+            Ok(0) => break,
+            Ok(read) => n += read,
+            Err(Error::INTERRUPTED) => {}
+            Err(_) => break,
+        }
+    }
+    _ = close(err_r);
+
+    if n == errno.len() {
+        // reap the child that failed to exec
+        _ = wait(pid);
+        return Err(Error::from_errno(u32::from_ne_bytes(errno) as usize));
+    }
+    Ok(pid)
+}
+
+/// Finds the program in the `PATH` of the child's environment.
+fn resolve(program: &str, env: &[&str], buf: &mut [u8; PATH_MAX]) -> Result<usize> {
+    const X_OK: usize = 1;
+
+    if program.contains('/') {
+        let len = program.len().min(PATH_MAX - 1);
+        buf[..len].copy_from_slice(&program.as_bytes()[..len]);
+        return Ok(len);
+    }
+
+    let paths = env.iter().find_map(|var| var.strip_prefix("PATH=")).unwrap_or("/bin:/usr/bin");
+    for dir in paths.split(':').filter(|dir| !dir.is_empty()) {
+        let len = dir.len() + 1 + program.len();
+        if len >= PATH_MAX {
+            continue;
+        }
+        buf[..dir.len()].copy_from_slice(dir.as_bytes());
+        buf[dir.len()] = b'/';
+        buf[dir.len() + 1..len].copy_from_slice(program.as_bytes());
+        buf[len] = 0;
+
+        // SAFETY: `buf` holds a C string
+        if check(unsafe { syscall2(ACCESS, buf.as_ptr() as usize, X_OK) }).is_ok() {
+            return Ok(len);
+        }
+    }
+
+    Err(Error::NOT_FOUND)
+}
+
+/// The C strings and pointer arrays for `execve`, in one mapping.
+struct ExecArgs {
+    map: usize,
+    map_len: usize,
+    path: usize,
+    argv: usize,
+    envp: usize,
+    cwd: usize,
+}
+
+impl ExecArgs {
+    fn new(
+        path: &[u8],
+        program: &str,
+        args: &[&str],
+        env: &[&str],
+        cwd: Option<&str>,
+    ) -> Result<Self> {
+        let ptrs = (args.len() + 2 + env.len() + 1) * mem::size_of::<usize>();
+        let string_len: usize = [path.len(), program.len(), cwd.map_or(0, str::len)]
+            .into_iter()
+            .chain(args.iter().map(|s| s.len()))
+            .chain(env.iter().map(|s| s.len()))
+            .map(|len| len + 1)
+            .sum();
+        let map_len = ptrs + string_len;
+        let map = mmap(map_len)?;
+
+        let mut strings = map + ptrs;
+        let mut push = |s: &[u8]| {
+            let at = strings;
+            // SAFETY: the mapping fits every string and its NUL
+            unsafe {
+                ptr::copy_nonoverlapping(s.as_ptr(), at as *mut u8, s.len());
+                *((at + s.len()) as *mut u8) = 0;
+            }
+            strings += s.len() + 1;
+            at
+        };
+
+        let path = push(path);
+        let cwd = cwd.map_or(0, |cwd| push(cwd.as_bytes()));
+
+        // argv: the program, then the args and a null
+        let argv = map;
+        let mut ptrs = argv as *mut usize;
+        for arg in [program].iter().chain(args) {
+            let arg = push(arg.as_bytes());
+            // SAFETY: the mapping starts with room for all pointers
+            unsafe {
+                *ptrs = arg;
+                ptrs = ptrs.add(1);
+            }
+        }
+        // SAFETY: as above, the mapping is zeroed so this is only for clarity
+        unsafe { *ptrs = 0 };
+
+        let envp = argv + (args.len() + 2) * mem::size_of::<usize>();
+        let mut ptrs = envp as *mut usize;
+        for var in env {
+            let var = push(var.as_bytes());
+            // SAFETY: the mapping starts with room for all pointers
+            unsafe {
+                *ptrs = var;
+                ptrs = ptrs.add(1);
+            }
+        }
+        // SAFETY: as above
+        unsafe { *ptrs = 0 };
+
+        Ok(Self { map, map_len, path, argv, envp, cwd })
+    }
+
+    /// Sets up the child's stdio and working directory and replaces the
+    /// child with the program.
+    ///
+    /// # Safety
+    ///
+    /// May only be called in a freshly forked child.
+    unsafe fn exec(&self, config: &LaunchConfig<'_>, err_pipe: FileDesc) -> ! {
+        let stdio = [(config.stdin, 0), (config.stdout, 1), (config.stderr, 2)];
+        let errno = |ret: usize| (ret > -4096isize as usize).then(|| ret.wrapping_neg());
+
+        // SAFETY: the child is single threaded and owns all of its memory
+        let err = (|| unsafe {
+            for (fd, target) in stdio {
+                let ret = if fd.0 == target {
+                    // the descriptor is already in place, it only has to survive the exec
+                    syscall3(FCNTL, fd.0, F_SETFD, 0)
+                } else {
+                    syscall2(DUP2, fd.0, target)
+                };
+                if let Some(err) = errno(ret) {
+                    return err;
+                }
+            }
+            if self.cwd != 0 {
+                if let Some(err) = errno(syscall1(CHDIR, self.cwd)) {
+                    return err;
+                }
+            }
+            // only returns if it failed
+            errno(syscall3(EXECVE, self.path, self.argv, self.envp)).unwrap_or(0)
+        })();
+
+        // tell the parent why the child couldn't start
+        _ = write(err_pipe, &(err as u32).to_ne_bytes());
+        // SAFETY: the child exits
+        unsafe { syscall1(EXIT_GROUP, 127) };
+        unreachable!()
+    }
+}
+
+impl Drop for ExecArgs {
+    fn drop(&mut self) {
+        // SAFETY: the parent is done with the mapping, the child has its own copy
+        _ = unsafe { munmap(self.map, self.map_len) };
+    }
+}
+
+/// Waits for the child to exit and returns its exit code, `128 + signal` if
+/// a signal killed it.
+pub fn wait(pid: usize) -> Result<i64> {
+    let mut status = 0i32;
+    // SAFETY: `status` is valid for writes
+    retry(|| unsafe { syscall4(WAIT4, pid, &mut status as *mut i32 as usize, 0, 0) })?;
+    Ok(exit_code(status))
+}
+
+/// Returns the child's exit code if it exited already.
+pub fn try_wait(pid: usize) -> Result<Option<i64>> {
+    const WNOHANG: usize = 1;
+    let mut status = 0i32;
+    // SAFETY: `status` is valid for writes
+    let ret =
+        retry(|| unsafe { syscall4(WAIT4, pid, &mut status as *mut i32 as usize, WNOHANG, 0) })?;
+    Ok((ret != 0).then(|| exit_code(status)))
+}
+
+fn exit_code(status: i32) -> i64 {
+    let signal = status & 0x7f;
+    if signal == 0 { ((status >> 8) & 0xff) as i64 } else { 128 + signal as i64 }
+}
+
+pub fn kill(pid: usize) -> Result<()> {
+    // SAFETY: only signals the child
+    check(unsafe { syscall2(KILL, pid, SIGKILL) }).map(|_| ())
+}
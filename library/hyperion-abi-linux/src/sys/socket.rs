@@ -0,0 +1,334 @@
+use core::fmt::{self, Write};
+use core::mem;
+use core::net::{Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6};
+
+use super::err::{Error, Result};
+use super::fs::FileDesc;
+use super::net::{Protocol, RecvFlags, Shutdown, SockOpt, SocketDomain, SocketType};
+use crate::linux::*;
+
+//
+
+// Hyperion passes socket addresses around as strings: `ip:port` for INET
+// sockets and a path for LOCAL ones.
+
+const AF_UNIX: u16 = 1;
+const AF_INET: u16 = 2;
+const AF_INET6: u16 = 10;
+
+const SOL_SOCKET: usize = 1;
+const SO_TYPE: usize = 3;
+const SO_ERROR: usize = 4;
+const SO_BROADCAST: usize = 6;
+const SO_RCVTIMEO: usize = 20;
+const SO_SNDTIMEO: usize = 21;
+const SO_DOMAIN: usize = 39;
+const IPPROTO_IP: usize = 0;
+const IPPROTO_TCP: usize = 6;
+const IPPROTO_IPV6: usize = 41;
+const IP_TTL: usize = 2;
+const TCP_NODELAY: usize = 1;
+const IPV6_UNICAST_HOPS: usize = 16;
+const IPV6_V6ONLY: usize = 26;
+
+const SOCK_CLOEXEC: usize = O_CLOEXEC;
+const MSG_PEEK: usize = 2;
+const MSG_NOSIGNAL: usize = 0x4000;
+
+/// `struct sockaddr_storage`
+#[repr(C, align(8))]
+struct SockAddr {
+    bytes: [u8; 128],
+    len: u32,
+}
+
+impl SockAddr {
+    fn empty() -> Self {
+        Self { bytes: [0; 128], len: 128 }
+    }
+
+    fn family(&self) -> u16 {
+        u16::from_ne_bytes([self.bytes[0], self.bytes[1]])
+    }
+
+    fn parse(fd: FileDesc, addr: &str) -> Result<Self> {
+        let mut sockaddr = Self::empty();
+        let bytes = &mut sockaddr.bytes;
+
+        if getsockopt_int(fd.0, SOL_SOCKET, SO_DOMAIN)? == SocketDomain::LOCAL.0 as u32 {
+            // `struct sockaddr_un`, the path has to be NUL terminated
+            if addr.len() >= 108 {
+                return Err(Error::INVALID_ADDRESS);
+            }
+            bytes[..2].copy_from_slice(&AF_UNIX.to_ne_bytes());
+            bytes[2..2 + addr.len()].copy_from_slice(addr.as_bytes());
+            sockaddr.len = (2 + addr.len() + 1) as u32;
+            return Ok(sockaddr);
+        }
+
+        match addr.parse::<SocketAddr>().map_err(|_| Error::INVALID_ADDRESS)? {
+            // `struct sockaddr_in`
+            SocketAddr::V4(addr) => {
+                bytes[..2].copy_from_slice(&AF_INET.to_ne_bytes());
+                bytes[2..4].copy_from_slice(&addr.port().to_be_bytes());
+                bytes[4..8].copy_from_slice(&addr.ip().octets());
+                sockaddr.len = 16;
+            }
+            // `struct sockaddr_in6`
+            SocketAddr::V6(addr) => {
+                bytes[..2].copy_from_slice(&AF_INET6.to_ne_bytes());
+                bytes[2..4].copy_from_slice(&addr.port().to_be_bytes());
+                bytes[4..8].copy_from_slice(&addr.flowinfo().to_be_bytes());
+                bytes[8..24].copy_from_slice(&addr.ip().octets());
+                bytes[24..28].copy_from_slice(&addr.scope_id().to_ne_bytes());
+                sockaddr.len = 28;
+            }
+        }
+        Ok(sockaddr)
+    }
+
+    /// Writes the address as a string into `buf`, returns its length.
+    fn format(&self, buf: &mut [u8]) -> Result<usize> {
+        let bytes = &self.bytes;
+        let port = u16::from_be_bytes([bytes[2], bytes[3]]);
+        let mut out = Cursor { buf, len: 0 };
+
+        let res = match self.family() {
+            AF_INET => {
+                let ip = Ipv4Addr::new(bytes[4], bytes[5], bytes[6], bytes[7]);
+                write!(out, "{}", SocketAddrV4::new(ip, port))
+            }
+            AF_INET6 => {
+                let flowinfo = u32::from_be_bytes(bytes[4..8].try_into().unwrap());
+                let ip = Ipv6Addr::from(<[u8; 16]>::try_from(&bytes[8..24]).unwrap());
+                let scope_id = u32::from_ne_bytes(bytes[24..28].try_into().unwrap());
+                write!(out, "{}", SocketAddrV6::new(ip, port, flowinfo, scope_id))
+            }
+            AF_UNIX => {
+                let path = &bytes[2..(self.len as usize).max(2)];
+                let path = &path[..path.iter().position(|&b| b == 0).unwrap_or(path.len())];
+                match core::str::from_utf8(path) {
+                    Ok(path) => out.write_str(path),
+                    Err(_) => return Err(Error::INVALID_UTF8),
+                }
+            }
+            _ => return Err(Error::INVALID_DOMAIN),
+        };
+
+        res.map_err(|_| Error::INVALID_ARGUMENT)?;
+        Ok(out.len)
+    }
+}
+
+struct Cursor<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+}
+
+impl Write for Cursor<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let buf = self.buf.get_mut(self.len..self.len + s.len()).ok_or(fmt::Error)?;
+        buf.copy_from_slice(s.as_bytes());
+        self.len += s.len();
+        Ok(())
+    }
+}
+
+//
+
+pub fn socket(domain: SocketDomain, ty: SocketType, _protocol: Protocol) -> Result<FileDesc> {
+    // the protocol follows from the domain and type
+    check(unsafe { syscall3(SOCKET, domain.0, ty.0 | SOCK_CLOEXEC, 0) }).map(FileDesc)
+}
+
+/// Binds the socket, stream sockets start listening right away.
+pub fn bind(fd: FileDesc, addr: &str) -> Result<()> {
+    let addr = SockAddr::parse(fd, addr)?;
+    check(unsafe { syscall3(BIND, fd.0, addr.bytes.as_ptr() as usize, addr.len as usize) })?;
+    if getsockopt_int(fd.0, SOL_SOCKET, SO_TYPE)? == SocketType::STREAM.0 as u32 {
+        check(unsafe { syscall2(LISTEN, fd.0, 128) })?;
+    }
+    Ok(())
+}
+
+pub fn accept(fd: FileDesc) -> Result<FileDesc> {
+    retry(|| unsafe { syscall4(ACCEPT4, fd.0, 0, 0, SOCK_CLOEXEC) }).map(FileDesc)
+}
+
+pub fn connect(fd: FileDesc, addr: &str) -> Result<()> {
+    let addr = SockAddr::parse(fd, addr)?;
+    check(unsafe { syscall3(CONNECT, fd.0, addr.bytes.as_ptr() as usize, addr.len as usize) })
+        .map(|_| ())
+}
+
+pub fn send(fd: FileDesc, buf: &[u8], _flags: usize) -> Result<usize> {
+    // a closed peer is an error, not a SIGPIPE
+    check(unsafe { syscall6(SENDTO, fd.0, buf.as_ptr() as usize, buf.len(), MSG_NOSIGNAL, 0, 0) })
+}
+
+pub fn send_to(fd: FileDesc, buf: &[u8], _flags: usize, addr: &str) -> Result<usize> {
+    let addr = SockAddr::parse(fd, addr)?;
+    check(unsafe {
+        syscall6(
+            SENDTO,
+            fd.0,
+            buf.as_ptr() as usize,
+            buf.len(),
+            MSG_NOSIGNAL,
+            addr.bytes.as_ptr() as usize,
+            addr.len as usize,
+        )
+    })
+}
+
+fn recv_flags(flags: usize) -> usize {
+    if flags & RecvFlags::PEEK.bits() != 0 { MSG_PEEK } else { 0 }
+}
+
+pub fn recv(fd: FileDesc, buf: &mut [u8], flags: usize) -> Result<usize> {
+    check(unsafe {
+        syscall6(RECVFROM, fd.0, buf.as_mut_ptr() as usize, buf.len(), recv_flags(flags), 0, 0)
+    })
+}
+
+/// Returns the number of bytes received and the length of the sender's
+/// address written into `addr`.
+pub fn recv_from(
+    fd: FileDesc,
+    buf: &mut [u8],
+    flags: usize,
+    addr: &mut [u8],
+) -> Result<(usize, usize)> {
+    let mut from = SockAddr::empty();
+    let n = check(unsafe {
+        syscall6(
+            RECVFROM,
+            fd.0,
+            buf.as_mut_ptr() as usize,
+            buf.len(),
+            recv_flags(flags),
+            from.bytes.as_mut_ptr() as usize,
+            &mut from.len as *mut u32 as usize,
+        )
+    })?;
+    Ok((n, from.format(addr)?))
+}
+
+/// Writes the peer's address into `addr`, returns its length.
+pub fn peer_addr(fd: FileDesc, addr: &mut [u8]) -> Result<usize> {
+    sock_name(GETPEERNAME, fd, addr)
+}
+
+/// Writes the socket's own address into `addr`, returns its length.
+pub fn local_addr(fd: FileDesc, addr: &mut [u8]) -> Result<usize> {
+    sock_name(GETSOCKNAME, fd, addr)
+}
+
+fn sock_name(syscall: usize, fd: FileDesc, addr: &mut [u8]) -> Result<usize> {
+    let mut name = SockAddr::empty();
+    check(unsafe {
+        syscall3(
+            syscall,
+            fd.0,
+            name.bytes.as_mut_ptr() as usize,
+            &mut name.len as *mut u32 as usize,
+        )
+    })?;
+    name.format(addr)
+}
+
+pub fn shutdown(fd: FileDesc, how: Shutdown) -> Result<()> {
+    check(unsafe { syscall2(SHUTDOWN, fd.0, how.0) }).map(|_| ())
+}
+
+//
+
+// Linux bounds a blocking `connect` with the send timeout, so the connect
+// timeout is the send timeout here, and changing one changes the other.
+
+pub fn get_sock_opt(fd: FileDesc, opt: SockOpt) -> Result<usize> {
+    let fd = fd.0;
+    let val = match opt {
+        SockOpt::READ_TIMEOUT => get_timeout(fd, SO_RCVTIMEO)?,
+        SockOpt::WRITE_TIMEOUT | SockOpt::CONNECT_TIMEOUT => get_timeout(fd, SO_SNDTIMEO)?,
+        SockOpt::NODELAY => getsockopt_int(fd, IPPROTO_TCP, TCP_NODELAY)? as usize,
+        SockOpt::TTL if is_v6(fd)? => getsockopt_int(fd, IPPROTO_IPV6, IPV6_UNICAST_HOPS)? as usize,
+        SockOpt::TTL => getsockopt_int(fd, IPPROTO_IP, IP_TTL)? as usize,
+        SockOpt::ONLY_V6 => getsockopt_int(fd, IPPROTO_IPV6, IPV6_V6ONLY)? as usize,
+        SockOpt::BROADCAST => getsockopt_int(fd, SOL_SOCKET, SO_BROADCAST)? as usize,
+        SockOpt::NONBLOCKING => {
+            let flags = check(unsafe { syscall2(FCNTL, fd, F_GETFL) })?;
+            (flags & O_NONBLOCK != 0) as usize
+        }
+        SockOpt::ERROR => match getsockopt_int(fd, SOL_SOCKET, SO_ERROR)? {
+            0 => 0,
+            errno => Error::from_errno(errno as usize).0,
+        },
+        _ => return Err(Error::INVALID_ARGUMENT),
+    };
+    Ok(val)
+}
+
+pub fn set_sock_opt(fd: FileDesc, opt: SockOpt, val: usize) -> Result<()> {
+    let fd = fd.0;
+    match opt {
+        SockOpt::READ_TIMEOUT => set_timeout(fd, SO_RCVTIMEO, val),
+        SockOpt::WRITE_TIMEOUT | SockOpt::CONNECT_TIMEOUT => set_timeout(fd, SO_SNDTIMEO, val),
+        SockOpt::NODELAY => setsockopt_int(fd, IPPROTO_TCP, TCP_NODELAY, val as u32),
+        SockOpt::TTL if is_v6(fd)? => {
+            setsockopt_int(fd, IPPROTO_IPV6, IPV6_UNICAST_HOPS, val as u32)
+        }
+        SockOpt::TTL => setsockopt_int(fd, IPPROTO_IP, IP_TTL, val as u32),
+        SockOpt::ONLY_V6 => setsockopt_int(fd, IPPROTO_IPV6, IPV6_V6ONLY, val as u32),
+        SockOpt::BROADCAST => setsockopt_int(fd, SOL_SOCKET, SO_BROADCAST, val as u32),
+        SockOpt::NONBLOCKING => super::file::set_nonblocking(FileDesc(fd), val != 0),
+        _ => Err(Error::INVALID_ARGUMENT),
+    }
+}
+
+fn is_v6(fd: usize) -> Result<bool> {
+    Ok(getsockopt_int(fd, SOL_SOCKET, SO_DOMAIN)? == SocketDomain::INET6.0 as u32)
+}
+
+/// `struct timeval`
+#[repr(C)]
+struct Timeval {
+    sec: i64,
+    usec: i64,
+}
+
+fn get_timeout(fd: usize, name: usize) -> Result<usize> {
+    let mut tv = Timeval { sec: 0, usec: 0 };
+    let mut len = mem::size_of::<Timeval>() as u32;
+    check(unsafe {
+        syscall5(
+            GETSOCKOPT,
+            fd,
+            SOL_SOCKET,
+            name,
+            &mut tv as *mut Timeval as usize,
+            &mut len as *mut u32 as usize,
+        )
+    })?;
+    Ok((tv.sec as usize).saturating_mul(1_000_000_000).saturating_add(tv.usec as usize * 1000))
+}
+
+fn set_timeout(fd: usize, name: usize, nanos: usize) -> Result<()> {
+    // round up, so a short timeout doesn't turn into no timeout at all
+    let micros = nanos.div_ceil(1000);
+    let tv = Timeval {
+        sec: (micros / 1_000_000).min(i64::MAX as usize) as i64,
+        usec: (micros % 1_000_000) as i64,
+    };
+    check(unsafe {
+        syscall5(
+            SETSOCKOPT,
+            fd,
+            SOL_SOCKET,
+            name,
+            &tv as *const Timeval as usize,
+            mem::size_of::<Timeval>(),
+        )
+    })
+    .map(|_| ())
+}
@@ -0,0 +1,300 @@
+use core::arch::{asm, global_asm};
+use core::ops::Range;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use super::err::{Error, Result};
+use crate::linux::*;
+use crate::start::{self, ALT_STACK_LEN};
+
+//
+
+/// Starts a new thread that calls `entry(stack_top, arg)`.
+///
+/// The stack gets an unmapped guard page below it, and is unmapped again
+/// together with the thread's TLS once the thread calls [`done`].
+pub fn spawn_with_stack(
+    entry: extern "C" fn(usize, usize) -> !,
+    arg: usize,
+    stack_len: usize,
+) -> Result<()> {
+    const FLAGS: usize = 0x100 // CLONE_VM
+        | 0x200 // CLONE_FS
+        | 0x400 // CLONE_FILES
+        | 0x800 // CLONE_SIGHAND
+        | 0x10000 // CLONE_THREAD
+        | 0x40000 // CLONE_SYSVSEM
+        | 0x80000; // CLONE_SETTLS
+
+    let tp = start::new_thread(stack_len)?;
+    // SAFETY: `new_thread` returns a thread pointer to a valid TCB
+    let tcb = unsafe { &*(tp as *const start::Tcb) };
+
+    // the new thread pops `entry` and `arg` off its stack
+    let sp = tcb.stack_top - 16;
+    // SAFETY: the stack is mapped and not used by anything yet
+    unsafe {
+        *(sp as *mut usize) = entry as usize;
+        *((sp + 8) as *mut usize) = arg;
+    }
+
+    let ret: usize;
+    // SAFETY: the new thread runs on its own stack and TLS, and never returns
+    // into this function
+    unsafe {
+        asm!(
+            "syscall",
+            "test rax, rax",
+            "jnz 2f",
+            "xor ebp, ebp",
+            "pop rdi",
+            "pop rsi",
+            "call {start}",
+            "ud2",
+            "2:",
+            start = sym thread_start,
+            inlateout("rax") CLONE => ret,
+            in("rdi") FLAGS,
+            in("rsi") sp,
+            in("rdx") 0,
+            in("r10") 0,
+            in("r8") tp,
+            lateout("rcx") _,
+            lateout("r11") _,
+            options(nostack),
+        );
+    }
+
+    if let Err(err) = check(ret) {
+        // SAFETY: the thread never started
+        _ = unsafe { munmap(tcb.map_base, tcb.map_len) };
+        return Err(err);
+    }
+    Ok(())
+}
+
+extern "C" fn thread_start(entry: extern "C" fn(usize, usize) -> !, arg: usize) -> ! {
+    let tcb = start::current();
+    set_alt_stack(tcb.alt_stack);
+    entry(tcb.stack_top, arg)
+}
+
+/// Exits the calling thread.
+pub fn done(_code: i64) -> ! {
+    let tcb = start::current();
+    let (map_base, map_len) = (tcb.map_base, tcb.map_len);
+
+    // SAFETY: all syscalls below only end this thread
+    unsafe {
+        if map_len == 0 {
+            // the main thread's stack belongs to the kernel
+            syscall1(EXIT, 0);
+            unreachable!();
+        }
+
+        // no signal handler may run once the stack is gone
+        let all: u64 = !0;
+        syscall4(RT_SIGPROCMASK, 0 /* SIG_BLOCK */, &all as *const u64 as usize, 0, 8);
+
+        // unmap the stack, TLS and TCB, and then exit without touching any of them
+        asm!(
+            "syscall",
+            "mov eax, edx",
+            "xor edi, edi",
+            "syscall",
+            "ud2",
+            in("rax") MUNMAP,
+            in("rdi") map_base,
+            in("rsi") map_len,
+            in("rdx") EXIT,
+            options(noreturn, nostack),
+        );
+    }
+}
+
+pub fn yield_now() {
+    // SAFETY: sched_yield has no arguments
+    unsafe { syscall0(SCHED_YIELD) };
+}
+
+pub fn nanosleep(nanos: u64) {
+    let Ok(now) = clock_gettime(CLOCK_MONOTONIC) else { return };
+    let deadline = Timespec::from_nanos(now.saturating_add(nanos));
+    // an absolute deadline doesn't drift when the sleep gets interrupted
+    _ = retry(|| unsafe {
+        syscall4(
+            CLOCK_NANOSLEEP,
+            CLOCK_MONOTONIC,
+            TIMER_ABSTIME,
+            &deadline as *const Timespec as usize,
+            0,
+        )
+    });
+}
+
+/// Renames the calling thread, Linux truncates the name to 15 bytes.
+pub fn rename(name: &str) -> Result<()> {
+    const PR_SET_NAME: usize = 15;
+    let mut buf = [0u8; 16];
+    let len = name.len().min(15);
+    buf[..len].copy_from_slice(&name.as_bytes()[..len]);
+    // SAFETY: `buf` is a valid C string
+    check(unsafe { syscall2(PRCTL, PR_SET_NAME, buf.as_ptr() as usize) }).map(|_| ())
+}
+
+/// Returns the number of CPUs the process may run on.
+pub fn cpu_count() -> usize {
+    let mut set = [0u64; 16];
+    // SAFETY: `set` is valid for writes of its size
+    let ret = unsafe {
+        syscall3(SCHED_GETAFFINITY, 0, core::mem::size_of_val(&set), set.as_mut_ptr() as usize)
+    };
+    match check(ret) {
+        Ok(_) => set.iter().map(|word| word.count_ones() as usize).sum(),
+        Err(_) => 0,
+    }
+}
+
+//
+
+// Linux futexes are 32 bits, the low half of the `usize` on little endian.
+// The full value is compared first, so a differing high half still returns
+// directly.
+
+pub fn futex_wait(futex: &AtomicUsize, expected: usize) {
+    if futex.load(Ordering::Relaxed) != expected {
+        return;
+    }
+    // SAFETY: the futex outlives the wait
+    unsafe {
+        syscall4(FUTEX, futex.as_ptr() as usize, FUTEX_WAIT_PRIVATE, expected as u32 as usize, 0)
+    };
+}
+
+/// Waits until `deadline`, in nanoseconds of [`super::timestamp`].
+pub fn futex_wait_until(futex: &AtomicUsize, expected: usize, deadline: u64) -> Result<()> {
+    if futex.load(Ordering::Relaxed) != expected {
+        return Ok(());
+    }
+    let deadline = Timespec::from_nanos(deadline);
+    // SAFETY: the futex outlives the wait
+    let ret = unsafe {
+        syscall6(
+            FUTEX,
+            futex.as_ptr() as usize,
+            FUTEX_WAIT_BITSET_PRIVATE,
+            expected as u32 as usize,
+            &deadline as *const Timespec as usize,
+            0,
+            FUTEX_BITSET_MATCH_ANY,
+        )
+    };
+    match check(ret) {
+        // the value already changed
+        Err(Error::WOULD_BLOCK) => Ok(()),
+        ret => ret.map(|_| ()),
+    }
+}
+
+pub fn futex_wake(futex: &AtomicUsize, count: usize) {
+    let count = count.min(i32::MAX as usize);
+    // SAFETY: waking doesn't access the futex
+    unsafe { syscall3(FUTEX, futex.as_ptr() as usize, FUTEX_WAKE_PRIVATE, count) };
+}
+
+//
+
+/// Returns the calling thread's guard page.
+pub fn stack_guard() -> Result<Range<usize>> {
+    let tcb = start::current();
+    if tcb.guard_end == 0 {
+        return Err(Error::NOT_FOUND);
+    }
+    Ok(tcb.guard_start..tcb.guard_end)
+}
+
+static FAULT_HANDLER: AtomicUsize = AtomicUsize::new(0);
+
+/// Calls `handler` with the faulting address on segfaults, on a separate
+/// stack. The process is killed once the handler returns.
+pub fn set_fault_handler(handler: extern "C" fn(usize)) -> Result<()> {
+    FAULT_HANDLER.store(handler as usize, Ordering::Release);
+    for sig in [SIGSEGV, SIGBUS] {
+        set_signal_handler(sig, on_fault as usize)?;
+    }
+    Ok(())
+}
+
+const SIGBUS: usize = 7;
+const SIGSEGV: usize = 11;
+const SIG_DFL: usize = 0;
+
+/// `struct sigaction` as the kernel takes it.
+#[repr(C)]
+struct SigAction {
+    handler: usize,
+    flags: usize,
+    restorer: usize,
+    mask: u64,
+}
+
+fn set_signal_handler(sig: usize, handler: usize) -> Result<()> {
+    const SA_SIGINFO: usize = 0x4;
+    const SA_ONSTACK: usize = 0x08000000;
+    const SA_RESTORER: usize = 0x04000000;
+
+    extern "C" {
+        fn __hyperion_linux_sigreturn();
+    }
+
+    let action = SigAction {
+        handler,
+        flags: SA_SIGINFO | SA_ONSTACK | SA_RESTORER,
+        restorer: __hyperion_linux_sigreturn as usize,
+        mask: 0,
+    };
+    // SAFETY: `action` is valid for reads
+    check(unsafe { syscall4(RT_SIGACTION, sig, &action as *const SigAction as usize, 0, 8) })
+        .map(|_| ())
+}
+
+// Linux returns from signal handlers through the restorer, which has to call
+// `rt_sigreturn` with the stack exactly as the handler left it.
+global_asm!(
+    ".globl __hyperion_linux_sigreturn",
+    ".hidden __hyperion_linux_sigreturn",
+    "__hyperion_linux_sigreturn:",
+    "mov eax, 15", // rt_sigreturn
+    "syscall",
+    "ud2",
+);
+
+extern "C" fn on_fault(sig: i32, info: *const u8, _context: usize) {
+    // `siginfo_t::si_addr`
+    // SAFETY: the kernel passes a valid `siginfo_t`
+    let addr = unsafe { *(info.add(16) as *const usize) };
+
+    let handler = FAULT_HANDLER.load(Ordering::Acquire);
+    if handler != 0 {
+        // SAFETY: only `set_fault_handler` stores handlers
+        let handler: extern "C" fn(usize) = unsafe { core::mem::transmute(handler) };
+        handler(addr);
+    }
+
+    // the fault happens again after returning, and kills the process this time
+    _ = set_signal_handler(sig as usize, SIG_DFL);
+}
+
+/// Makes signal handlers of the calling thread run on `stack`.
+pub(crate) fn set_alt_stack(stack: usize) {
+    /// `stack_t`
+    #[repr(C)]
+    struct SigAltStack {
+        sp: usize,
+        flags: i32,
+        size: usize,
+    }
+
+    let stack = SigAltStack { sp: stack, flags: 0, size: ALT_STACK_LEN };
+    // SAFETY: the stack stays mapped for as long as the thread runs
+    unsafe { syscall2(SIGALTSTACK, &stack as *const SigAltStack as usize, 0) };
+}
@@ -50,6 +50,7 @@
         all(target_family = "windows", target_env = "gnu"),
         target_os = "psp",
         target_os = "xous",
+        target_os = "hyperion",
         target_os = "solid_asp3",
         all(target_family = "unix", not(target_os = "espidf")),
         all(target_vendor = "fortanix", target_env = "sgx"),
@@ -441,6 +441,12 @@
 ))]
 extern crate miniz_oxide;
 
+// The Linux-hosted Hyperion target swaps the kernel ABI for a shim with the
+// same API, Cargo doesn't allow the same dependency name to have different
+// sources per target.
+#[cfg(all(target_os = "hyperion", target_env = "hosted"))]
+extern crate hyperion_abi_linux as hyperion_abi;
+
 // During testing, this crate is not actually the "real" std library, but rather
 // it links to the real std library, which was compiled from this same source
 // code. So any lang items std defines are conditionally excluded (or else they
@@ -650,9 +656,13 @@ pub mod arch {
 // Private support modules
 mod panicking;
 
+#[cfg(not(target_os = "hyperion"))]
 #[path = "../../backtrace/src/lib.rs"]
 #[allow(dead_code, unused_attributes, fuzzy_provenance_casts)]
 mod backtrace_rs;
+// backtrace-rs doesn't support Hyperion, its PAL provides the API std uses
+#[cfg(target_os = "hyperion")]
+use sys::backtrace as backtrace_rs;
 
 // Re-export macros defined in core.
 #[stable(feature = "rust1", since = "1.0.0")]
@@ -0,0 +1,230 @@
+//! Hyperion-specific extensions to primitives in the [`std::fs`] module.
+//!
+//! [`std::fs`]: crate::fs
+
+#![stable(feature = "rust1", since = "1.0.0")]
+#![deny(unsafe_op_in_unsafe_fn)]
+
+use hyperion_abi::sys::{palloc, pfree};
+
+use crate::fmt;
+use crate::fs;
+use crate::io;
+use crate::ops::{Deref, DerefMut};
+use crate::os::hyperion::map_sys_err;
+use crate::ptr::NonNull;
+use crate::slice;
+use crate::sys::alloc::PAGE_SIZE;
+use crate::sys_common::AsInner;
+
+//
+
+/// Hyperion-specific extensions to [`fs::File`].
+///
+/// None of these use or move the file's cursor, so several threads can read
+/// and write the same file at different offsets. Descriptors that can't seek,
+/// like pipes, fail with [`io::ErrorKind::NotSeekable`], and filesystems
+/// without positional I/O fail with [`io::ErrorKind::Unsupported`].
+#[stable(feature = "rust1", since = "1.0.0")]
+pub trait FileExt {
+    /// Reads some bytes starting at `offset`, returns how many were read.
+    ///
+    /// `Ok(0)` means the offset is at or past the end of the file.
+    #[stable(feature = "rust1", since = "1.0.0")]
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize>;
+
+    /// Writes some bytes starting at `offset`, returns how many were written.
+    ///
+    /// Writing past the end of the file extends it. Files opened for
+    /// appending may ignore `offset`.
+    #[stable(feature = "rust1", since = "1.0.0")]
+    fn write_at(&self, buf: &[u8], offset: u64) -> io::Result<usize>;
+
+    /// Fills all of `buf` starting at `offset`.
+    ///
+    /// Fails with [`io::ErrorKind::UnexpectedEof`] if the file ends first, the
+    /// contents of `buf` are unspecified then.
+    #[stable(feature = "rust1", since = "1.0.0")]
+    fn read_exact_at(&self, mut buf: &mut [u8], mut offset: u64) -> io::Result<()> {
+        while !buf.is_empty() {
+            match self.read_at(buf, offset) {
+                Ok(0) => break,
+                Ok(n) => {
+                    buf = &mut buf[n..];
+                    offset += n as u64;
+                }
+                Err(ref e) if e.is_interrupted() => {}
+                Err(e) => return Err(e),
+            }
+        }
+        if !buf.is_empty() {
+            Err(io::const_io_error!(io::ErrorKind::UnexpectedEof, "failed to fill whole buffer"))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Writes all of `buf` starting at `offset`.
+    #[stable(feature = "rust1", since = "1.0.0")]
+    fn write_all_at(&self, mut buf: &[u8], mut offset: u64) -> io::Result<()> {
+        while !buf.is_empty() {
+            match self.write_at(buf, offset) {
+                Ok(0) => {
+                    return Err(io::const_io_error!(
+                        io::ErrorKind::WriteZero,
+                        "failed to write whole buffer",
+                    ));
+                }
+                Ok(n) => {
+                    buf = &buf[n..];
+                    offset += n as u64;
+                }
+                Err(ref e) if e.is_interrupted() => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+
+    /// Maps `len` bytes of the file starting at `offset` into memory.
+    ///
+    /// See [`Mapping`] for how changes get back into the file.
+    #[stable(feature = "rust1", since = "1.0.0")]
+    fn map(&self, offset: u64, len: usize) -> io::Result<Mapping<'_>>;
+}
+
+#[stable(feature = "rust1", since = "1.0.0")]
+impl FileExt for fs::File {
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        self.as_inner().read_at(buf, offset)
+    }
+
+    fn write_at(&self, buf: &[u8], offset: u64) -> io::Result<usize> {
+        self.as_inner().write_at(buf, offset)
+    }
+
+    fn map(&self, offset: u64, len: usize) -> io::Result<Mapping<'_>> {
+        Mapping::new(self, offset, len)
+    }
+}
+
+//
+
+/// A range of a file, loaded into its own pages.
+///
+/// The kernel has no file-backed mappings, so the range is read into fresh
+/// pages when it is mapped and other writes to the file don't show up in it
+/// afterwards. Changes to the mapping reach the file only through
+/// [`Mapping::flush`], dropping the mapping discards them.
+///
+/// Bytes past the end of the file read as zeroes, flushing them extends the
+/// file.
+///
+/// ```no_run
+/// use std::fs::OpenOptions;
+/// use std::os::hyperion::fs::FileExt;
+///
+/// let file = OpenOptions::new().read(true).write(true).open("/var/db/pages")?;
+/// let mut page = file.map(4096, 4096)?;
+/// page[..4].copy_from_slice(b"used");
+/// page.flush()?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[stable(feature = "rust1", since = "1.0.0")]
+pub struct Mapping<'f> {
+    file: &'f fs::File,
+    offset: u64,
+    ptr: NonNull<u8>,
+    len: usize,
+}
+
+// SAFETY: the mapping owns its pages, like a `Box<[u8]>`
+#[stable(feature = "rust1", since = "1.0.0")]
+unsafe impl Send for Mapping<'_> {}
+#[stable(feature = "rust1", since = "1.0.0")]
+unsafe impl Sync for Mapping<'_> {}
+
+impl<'f> Mapping<'f> {
+    fn new(file: &'f fs::File, offset: u64, len: usize) -> io::Result<Self> {
+        offset.checked_add(len as u64).ok_or(io::const_io_error!(
+            io::ErrorKind::InvalidInput,
+            "the mapping would end past the largest file offset"
+        ))?;
+
+        // an empty mapping has no pages, so `Drop` has nothing to free
+        let ptr = if len == 0 {
+            NonNull::dangling()
+        } else {
+            // fresh pages are zeroed, which covers the part past the end of the file
+            palloc(len.div_ceil(PAGE_SIZE)).map_err(map_sys_err)?.ok_or(io::const_io_error!(
+                io::ErrorKind::OutOfMemory,
+                "no memory left to map the file"
+            ))?
+        };
+        let mut map = Self { file, offset, ptr, len };
+
+        let mut filled = 0;
+        while filled < len {
+            match file.read_at(&mut map[filled..], offset + filled as u64) {
+                Ok(0) => break,
+                Ok(n) => filled += n,
+                Err(ref e) if e.is_interrupted() => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(map)
+    }
+
+    /// The offset in the file the mapping starts at.
+    #[stable(feature = "rust1", since = "1.0.0")]
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Writes the mapped bytes back to the file, but not the rest of the last
+    /// page, which would grow the file.
+    ///
+    /// The file has to be opened for writing.
+    #[stable(feature = "rust1", since = "1.0.0")]
+    pub fn flush(&self) -> io::Result<()> {
+        self.file.write_all_at(&self[..self.len], self.offset)
+    }
+}
+
+#[stable(feature = "rust1", since = "1.0.0")]
+impl Deref for Mapping<'_> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        // SAFETY: the pages are mapped and zeroed for at least `len` bytes
+        unsafe { slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+#[stable(feature = "rust1", since = "1.0.0")]
+impl DerefMut for Mapping<'_> {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        // SAFETY: as above, and the pages belong to this mapping alone
+        unsafe { slice::from_raw_parts_mut(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+#[stable(feature = "rust1", since = "1.0.0")]
+impl Drop for Mapping<'_> {
+    fn drop(&mut self) {
+        if self.len != 0 {
+            pfree(self.ptr, self.len.div_ceil(PAGE_SIZE)).unwrap();
+        }
+    }
+}
+
+#[stable(feature = "rust1", since = "1.0.0")]
+impl fmt::Debug for Mapping<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Mapping")
+            .field("file", self.file)
+            .field("offset", &self.offset)
+            .field("len", &self.len)
+            .finish()
+    }
+}
@@ -0,0 +1,461 @@
+//! Owned and borrowed Hyperion file descriptors.
+//!
+//! This mirrors the I/O-safety types of `std::os::fd`, but uses Hyperion's
+//! native `usize` file descriptors.
+
+#![stable(feature = "rust1", since = "1.0.0")]
+#![deny(unsafe_op_in_unsafe_fn)]
+
+use hyperion_abi::sys::fs::FileDesc;
+use hyperion_abi::sys::{close, dup, set_nonblocking};
+
+use crate::fmt;
+use crate::fs;
+use crate::io;
+use crate::marker::PhantomData;
+use crate::mem::forget;
+use crate::os::hyperion::map_sys_err;
+use crate::process;
+use crate::sys::fs::File;
+use crate::sys::pipe::AnonPipe;
+use crate::sys_common::{AsInner, FromInner, IntoInner};
+
+//
+
+/// Raw file descriptors.
+#[stable(feature = "rust1", since = "1.0.0")]
+pub type RawFd = usize;
+
+/// A trait to extract the raw file descriptor from an underlying object.
+#[stable(feature = "rust1", since = "1.0.0")]
+pub trait AsRawFd {
+    /// Extracts the raw file descriptor.
+    ///
+    /// The returned descriptor is not owned by the caller and stays valid
+    /// only as long as the object it came from.
+    #[stable(feature = "rust1", since = "1.0.0")]
+    fn as_raw_fd(&self) -> RawFd;
+}
+
+/// A trait to construct an object from a raw file descriptor.
+#[stable(feature = "rust1", since = "1.0.0")]
+pub trait FromRawFd {
+    /// Constructs a new instance of `Self` from the given raw file descriptor.
+    ///
+    /// # Safety
+    ///
+    /// The `fd` passed in must be an open file descriptor that is not owned
+    /// by anything else, the returned object takes over closing it.
+    #[stable(feature = "rust1", since = "1.0.0")]
+    unsafe fn from_raw_fd(fd: RawFd) -> Self;
+}
+
+/// A trait to take ownership of the raw file descriptor of an object.
+#[stable(feature = "rust1", since = "1.0.0")]
+pub trait IntoRawFd {
+    /// Consumes this object, returning the raw file descriptor.
+    ///
+    /// The caller becomes responsible for closing the returned descriptor.
+    #[stable(feature = "rust1", since = "1.0.0")]
+    fn into_raw_fd(self) -> RawFd;
+}
+
+#[stable(feature = "rust1", since = "1.0.0")]
+impl AsRawFd for RawFd {
+    #[inline]
+    fn as_raw_fd(&self) -> RawFd {
+        *self
+    }
+}
+
+#[stable(feature = "rust1", since = "1.0.0")]
+impl IntoRawFd for RawFd {
+    #[inline]
+    fn into_raw_fd(self) -> RawFd {
+        self
+    }
+}
+
+#[stable(feature = "rust1", since = "1.0.0")]
+impl FromRawFd for RawFd {
+    #[inline]
+    unsafe fn from_raw_fd(fd: RawFd) -> RawFd {
+        fd
+    }
+}
+
+//
+
+/// A borrowed file descriptor.
+///
+/// This has a lifetime parameter to tie it to the lifetime of something that
+/// owns the file descriptor. For the duration of that lifetime, it is
+/// guaranteed that nobody will close the file descriptor.
+#[derive(Copy, Clone)]
+#[repr(transparent)]
+#[stable(feature = "rust1", since = "1.0.0")]
+pub struct BorrowedFd<'fd> {
+    fd: RawFd,
+    _phantom: PhantomData<&'fd OwnedFd>,
+}
+
+/// An owned file descriptor.
+///
+/// This closes the file descriptor on drop. It is guaranteed that nobody else
+/// will close the file descriptor.
+#[repr(transparent)]
+#[stable(feature = "rust1", since = "1.0.0")]
+pub struct OwnedFd {
+    fd: RawFd,
+}
+
+impl BorrowedFd<'_> {
+    /// Return a `BorrowedFd` holding the given raw file descriptor.
+    ///
+    /// # Safety
+    ///
+    /// The resource pointed to by `fd` must remain open for the duration of
+    /// the returned `BorrowedFd`.
+    #[inline]
+    #[rustc_const_stable(feature = "rust1", since = "1.0.0")]
+    #[stable(feature = "rust1", since = "1.0.0")]
+    pub const unsafe fn borrow_raw(fd: RawFd) -> Self {
+        Self { fd, _phantom: PhantomData }
+    }
+}
+
+impl OwnedFd {
+    /// Creates a new `OwnedFd` instance that shares the same underlying file
+    /// description as the existing `OwnedFd` instance.
+    #[stable(feature = "rust1", since = "1.0.0")]
+    pub fn try_clone(&self) -> io::Result<Self> {
+        self.as_fd().try_clone_to_owned()
+    }
+}
+
+impl BorrowedFd<'_> {
+    /// Creates a new `OwnedFd` instance that shares the same underlying file
+    /// description as the existing `BorrowedFd` instance.
+    #[stable(feature = "rust1", since = "1.0.0")]
+    pub fn try_clone_to_owned(&self) -> io::Result<OwnedFd> {
+        let fd = dup(FileDesc(self.fd)).map_err(map_sys_err)?;
+        // SAFETY: `dup` returned a new fd that nothing else owns
+        Ok(unsafe { OwnedFd::from_raw_fd(fd.0) })
+    }
+
+    /// Moves the file descriptor into or out of non-blocking mode.
+    ///
+    /// In non-blocking mode, reads and writes that can't make progress right
+    /// away fail with [`io::ErrorKind::WouldBlock`] instead of waiting. Use
+    /// [`poll`](crate::os::hyperion::poll::poll) to wait until they can.
+    ///
+    /// The mode belongs to the open file, so it is shared with every
+    /// duplicate of this descriptor.
+    #[stable(feature = "rust1", since = "1.0.0")]
+    pub fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        set_nonblocking(FileDesc(self.fd), nonblocking).map_err(map_sys_err)
+    }
+}
+
+#[stable(feature = "rust1", since = "1.0.0")]
+impl AsRawFd for BorrowedFd<'_> {
+    #[inline]
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd
+    }
+}
+
+#[stable(feature = "rust1", since = "1.0.0")]
+impl AsRawFd for OwnedFd {
+    #[inline]
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd
+    }
+}
+
+#[stable(feature = "rust1", since = "1.0.0")]
+impl IntoRawFd for OwnedFd {
+    #[inline]
+    fn into_raw_fd(self) -> RawFd {
+        let fd = self.fd;
+        forget(self);
+        fd
+    }
+}
+
+#[stable(feature = "rust1", since = "1.0.0")]
+impl FromRawFd for OwnedFd {
+    /// Constructs a new instance of `Self` from the given raw file descriptor.
+    ///
+    /// # Safety
+    ///
+    /// The resource pointed to by `fd` must be open and suitable for assuming
+    /// ownership. The resource must not require any cleanup other than `close`.
+    #[inline]
+    unsafe fn from_raw_fd(fd: RawFd) -> Self {
+        Self { fd }
+    }
+}
+
+#[stable(feature = "rust1", since = "1.0.0")]
+impl Drop for OwnedFd {
+    #[inline]
+    fn drop(&mut self) {
+        // errors are ignored, retrying could close an unrelated fd that was
+        // opened after this one got closed
+        let _ = close(FileDesc(self.fd));
+    }
+}
+
+#[stable(feature = "rust1", since = "1.0.0")]
+impl fmt::Debug for BorrowedFd<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BorrowedFd").field("fd", &self.fd).finish()
+    }
+}
+
+#[stable(feature = "rust1", since = "1.0.0")]
+impl fmt::Debug for OwnedFd {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OwnedFd").field("fd", &self.fd).finish()
+    }
+}
+
+macro_rules! impl_is_terminal {
+    ($($t:ty),*$(,)?) => {$(
+        #[unstable(feature = "sealed", issue = "none")]
+        impl crate::sealed::Sealed for $t {}
+
+        #[stable(feature = "is_terminal", since = "1.70.0")]
+        impl crate::io::IsTerminal for $t {
+            #[inline]
+            fn is_terminal(&self) -> bool {
+                crate::sys::io::is_terminal(self)
+            }
+        }
+    )*}
+}
+
+impl_is_terminal!(BorrowedFd<'_>, OwnedFd);
+
+//
+
+/// A trait to borrow the file descriptor from an underlying object.
+#[stable(feature = "rust1", since = "1.0.0")]
+pub trait AsFd {
+    /// Borrows the file descriptor.
+    #[stable(feature = "rust1", since = "1.0.0")]
+    fn as_fd(&self) -> BorrowedFd<'_>;
+}
+
+#[stable(feature = "rust1", since = "1.0.0")]
+impl<T: AsFd> AsFd for &T {
+    #[inline]
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        T::as_fd(self)
+    }
+}
+
+#[stable(feature = "rust1", since = "1.0.0")]
+impl<T: AsFd> AsFd for &mut T {
+    #[inline]
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        T::as_fd(self)
+    }
+}
+
+#[stable(feature = "rust1", since = "1.0.0")]
+impl<T: AsFd> AsFd for crate::sync::Arc<T> {
+    #[inline]
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        (**self).as_fd()
+    }
+}
+
+#[stable(feature = "rust1", since = "1.0.0")]
+impl<T: AsFd> AsFd for crate::rc::Rc<T> {
+    #[inline]
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        (**self).as_fd()
+    }
+}
+
+#[stable(feature = "rust1", since = "1.0.0")]
+impl<T: AsFd> AsFd for Box<T> {
+    #[inline]
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        (**self).as_fd()
+    }
+}
+
+#[stable(feature = "rust1", since = "1.0.0")]
+impl AsFd for BorrowedFd<'_> {
+    #[inline]
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        *self
+    }
+}
+
+#[stable(feature = "rust1", since = "1.0.0")]
+impl AsFd for OwnedFd {
+    #[inline]
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        // SAFETY: the `BorrowedFd` is bounded by the lifetime of `&self`
+        unsafe { BorrowedFd::borrow_raw(self.fd) }
+    }
+}
+
+//
+
+impl IntoInner<File> for OwnedFd {
+    #[inline]
+    fn into_inner(self) -> File {
+        File::from_inner(FileDesc(self.into_raw_fd()))
+    }
+}
+
+impl FromInner<File> for OwnedFd {
+    #[inline]
+    fn from_inner(file: File) -> Self {
+        // SAFETY: the fd came from a `File`, which owned it
+        unsafe { Self::from_raw_fd(file.into_inner().0) }
+    }
+}
+
+#[stable(feature = "rust1", since = "1.0.0")]
+impl AsRawFd for fs::File {
+    #[inline]
+    fn as_raw_fd(&self) -> RawFd {
+        self.as_inner().as_inner().0
+    }
+}
+
+#[stable(feature = "rust1", since = "1.0.0")]
+impl FromRawFd for fs::File {
+    #[inline]
+    unsafe fn from_raw_fd(fd: RawFd) -> fs::File {
+        // SAFETY: the caller guarantees that `fd` is owned
+        Self::from(unsafe { OwnedFd::from_raw_fd(fd) })
+    }
+}
+
+#[stable(feature = "rust1", since = "1.0.0")]
+impl IntoRawFd for fs::File {
+    #[inline]
+    fn into_raw_fd(self) -> RawFd {
+        OwnedFd::from(self).into_raw_fd()
+    }
+}
+
+#[stable(feature = "rust1", since = "1.0.0")]
+impl AsFd for fs::File {
+    #[inline]
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        // SAFETY: the `BorrowedFd` is bounded by the lifetime of `&self`
+        unsafe { BorrowedFd::borrow_raw(self.as_raw_fd()) }
+    }
+}
+
+#[stable(feature = "rust1", since = "1.0.0")]
+impl From<fs::File> for OwnedFd {
+    /// Takes ownership of a [`File`](fs::File)'s underlying file descriptor.
+    #[inline]
+    fn from(file: fs::File) -> OwnedFd {
+        OwnedFd::from_inner(file.into_inner())
+    }
+}
+
+#[stable(feature = "rust1", since = "1.0.0")]
+impl From<OwnedFd> for fs::File {
+    /// Returns a [`File`](fs::File) that takes ownership of the given
+    /// file descriptor.
+    #[inline]
+    fn from(owned_fd: OwnedFd) -> Self {
+        Self::from_inner(owned_fd.into_inner())
+    }
+}
+
+//
+
+macro_rules! impl_stdio {
+    ($($t:ty => $fd:literal),* $(,)?) => {$(
+        #[stable(feature = "rust1", since = "1.0.0")]
+        impl AsRawFd for $t {
+            #[inline]
+            fn as_raw_fd(&self) -> RawFd {
+                $fd
+            }
+        }
+
+        #[stable(feature = "rust1", since = "1.0.0")]
+        impl AsFd for $t {
+            #[inline]
+            fn as_fd(&self) -> BorrowedFd<'_> {
+                // SAFETY: user code should not close stdio out from under the standard library
+                unsafe { BorrowedFd::borrow_raw($fd) }
+            }
+        }
+    )*};
+}
+
+impl_stdio! {
+    io::Stdin => 0,
+    io::StdinLock<'_> => 0,
+    io::Stdout => 1,
+    io::StdoutLock<'_> => 1,
+    io::Stderr => 2,
+    io::StderrLock<'_> => 2,
+}
+
+//
+
+macro_rules! impl_child_pipe {
+    ($($t:ty),* $(,)?) => {$(
+        #[stable(feature = "rust1", since = "1.0.0")]
+        impl AsRawFd for $t {
+            #[inline]
+            fn as_raw_fd(&self) -> RawFd {
+                self.as_inner().as_inner().as_inner().0
+            }
+        }
+
+        #[stable(feature = "rust1", since = "1.0.0")]
+        impl AsFd for $t {
+            #[inline]
+            fn as_fd(&self) -> BorrowedFd<'_> {
+                // SAFETY: the `BorrowedFd` is bounded by the lifetime of `&self`
+                unsafe { BorrowedFd::borrow_raw(self.as_raw_fd()) }
+            }
+        }
+
+        #[stable(feature = "rust1", since = "1.0.0")]
+        impl IntoRawFd for $t {
+            #[inline]
+            fn into_raw_fd(self) -> RawFd {
+                OwnedFd::from(self).into_raw_fd()
+            }
+        }
+
+        #[stable(feature = "rust1", since = "1.0.0")]
+        impl From<$t> for OwnedFd {
+            /// Takes ownership of the child pipe's file descriptor.
+            #[inline]
+            fn from(pipe: $t) -> OwnedFd {
+                OwnedFd::from_inner(pipe.into_inner().into_inner())
+            }
+        }
+
+        #[stable(feature = "rust1", since = "1.0.0")]
+        impl From<OwnedFd> for $t {
+            /// Creates a child pipe end from the given file descriptor.
+            ///
+            /// The descriptor is expected to be a pipe end, it is not checked.
+            #[inline]
+            fn from(owned_fd: OwnedFd) -> Self {
+                Self::from_inner(AnonPipe::from_fd(FileDesc(owned_fd.into_raw_fd())))
+            }
+        }
+    )*};
+}
+
+impl_child_pipe!(process::ChildStdin, process::ChildStdout, process::ChildStderr);
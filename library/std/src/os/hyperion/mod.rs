@@ -4,25 +4,29 @@
 
 use hyperion_abi::sys::err::Error;
 
-use crate::{fs, io, sys_common::AsInner};
+use crate::io as std_io;
 
+#[stable(feature = "rust1", since = "1.0.0")]
+pub mod fs;
+#[stable(feature = "rust1", since = "1.0.0")]
+pub mod io;
 #[stable(feature = "rust1", since = "1.0.0")]
 pub mod net;
-
-//
+#[stable(feature = "rust1", since = "1.0.0")]
+pub mod poll;
 
 #[stable(feature = "rust1", since = "1.0.0")]
-pub trait AsRawFd {
-    #[stable(feature = "rust1", since = "1.0.0")]
-    fn as_raw_fd(&self) -> usize;
-}
+pub use self::io::{AsFd, AsRawFd, BorrowedFd, FromRawFd, IntoRawFd, OwnedFd, RawFd};
+
+//
 
+/// Fills `buf` with random bytes from the same source that seeds
+/// `HashMap`'s `RandomState`.
+///
+/// Either the whole buffer is filled or an error is returned.
 #[stable(feature = "rust1", since = "1.0.0")]
-impl AsRawFd for fs::File {
-    #[inline]
-    fn as_raw_fd(&self) -> usize {
-        self.as_inner().as_inner().0
-    }
+pub fn fill_random(buf: &mut [u8]) -> std_io::Result<()> {
+    crate::sys::rand::fill_bytes(buf)
 }
 
 //
@@ -31,16 +35,26 @@ pub(crate) fn to_sys_err(err: i32) -> Error {
     Error(usize::try_from(err).unwrap_or(usize::MAX))
 }
 
-pub(crate) fn map_sys_err(err: Error) -> io::Error {
-    io::Error::from_raw_os_error(err.0 as i32)
+pub(crate) fn map_sys_err(err: Error) -> std_io::Error {
+    crate::sys::os::set_errno(err.0 as i32);
+    std_io::Error::from_raw_os_error(err.0 as i32)
 }
 
-pub(crate) fn sys_err_kind(err: Error) -> io::ErrorKind {
+pub(crate) fn sys_err_kind(err: Error) -> std_io::ErrorKind {
+    // `self::io` shadows std's `io` everywhere else in this module
+    use crate::io;
+
     match err {
+        Error::ADDR_IN_USE => io::ErrorKind::AddrInUse,
+        Error::ADDR_NOT_AVAILABLE => io::ErrorKind::AddrNotAvailable,
         Error::ALREADY_EXISTS => io::ErrorKind::AlreadyExists,
         Error::BAD_FILE_DESCRIPTOR => io::ErrorKind::NotFound,
         Error::CLOSED => io::ErrorKind::UnexpectedEof,
+        Error::CONNECTION_ABORTED => io::ErrorKind::ConnectionAborted,
         Error::CONNECTION_REFUSED => io::ErrorKind::ConnectionRefused,
+        Error::CONNECTION_RESET => io::ErrorKind::ConnectionReset,
+        Error::CROSSES_DEVICES => io::ErrorKind::CrossesDevices,
+        Error::DIRECTORY_NOT_EMPTY => io::ErrorKind::DirectoryNotEmpty,
         Error::FILESYSTEM_ERROR => io::ErrorKind::Uncategorized,
         Error::INTERRUPTED => io::ErrorKind::Interrupted,
         Error::INVALID_ADDRESS => io::ErrorKind::InvalidInput,
@@ -50,17 +64,22 @@ pub(crate) fn sys_err_kind(err: Error) -> io::ErrorKind {
         Error::INVALID_FLAGS => io::ErrorKind::InvalidInput,
         Error::INVALID_TYPE => io::ErrorKind::InvalidInput,
         Error::INVALID_UTF8 => io::ErrorKind::InvalidData,
-        Error::IS_A_PIPE => io::ErrorKind::InvalidInput,
+        Error::IS_A_PIPE => io::ErrorKind::NotSeekable,
         Error::NOT_A_DIRECTORY => io::ErrorKind::NotADirectory,
         Error::NOT_A_FILE => io::ErrorKind::IsADirectory,
         Error::NOT_A_SOCKET => io::ErrorKind::InvalidInput,
+        Error::NOT_A_SYMLINK => io::ErrorKind::InvalidInput,
+        Error::NOT_CONNECTED => io::ErrorKind::NotConnected,
         Error::NOT_FOUND => io::ErrorKind::NotFound,
         Error::NO_SUCH_PROCESS => io::ErrorKind::NotFound,
         Error::OUT_OF_MEMORY => io::ErrorKind::OutOfMemory,
         Error::OUT_OF_VIRTUAL_MEMORY => io::ErrorKind::OutOfMemory,
         Error::PERMISSION_DENIED => io::ErrorKind::PermissionDenied,
+        Error::TIMED_OUT => io::ErrorKind::TimedOut,
         Error::UNEXPECTED_EOF => io::ErrorKind::UnexpectedEof,
         Error::UNKNOWN_PROTOCOL => io::ErrorKind::InvalidInput,
+        Error::UNSUPPORTED => io::ErrorKind::Unsupported,
+        Error::WOULD_BLOCK => io::ErrorKind::WouldBlock,
         Error::WRITE_ZERO => io::ErrorKind::WriteZero,
         _ => io::ErrorKind::Uncategorized,
     }
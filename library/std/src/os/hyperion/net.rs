@@ -1,6 +1,5 @@
 use crate::{
     io::{Read, Write},
-    mem::forget,
     sync::Arc,
 };
 
@@ -12,7 +11,7 @@
 
 use crate::io;
 
-use super::map_sys_err;
+use super::{map_sys_err, AsFd, AsRawFd, BorrowedFd, FromRawFd, IntoRawFd, OwnedFd, RawFd};
 
 //
 
@@ -33,22 +32,26 @@ impl LocalListener {
     pub fn bind(addr: &str) -> io::Result<Self> {
         let fd = socket(SocketDomain::LOCAL, SocketType::STREAM, Protocol::LOCAL)
             .map_err(map_sys_err)?;
-        bind(fd, addr).map_err(map_sys_err)?;
+        // owned right away, so the socket is closed if `bind` fails
+        // SAFETY: `socket` just returned the fd, nothing else owns it
+        let this = Self { fd: unsafe { OwnedFd::from_raw_fd(fd.0) } };
+        bind(this.fd(), addr).map_err(map_sys_err)?;
 
-        Ok(Self { fd: OwnedFd(fd) })
+        Ok(this)
     }
 
     /// the file descriptor won't be closed automatically
     #[stable(feature = "rust1", since = "1.0.0")]
     #[must_use]
     pub fn leak_fd(self) -> FileDesc {
-        self.fd.leak_fd()
+        FileDesc(self.fd.into_raw_fd())
     }
 
     #[stable(feature = "rust1", since = "1.0.0")]
     pub fn accept(&self) -> io::Result<LocalStream> {
-        let fd = accept(self.fd.0).map_err(map_sys_err)?;
-        Ok(LocalStream { fd: OwnedFd(fd) })
+        let fd = accept(self.fd()).map_err(map_sys_err)?;
+        // SAFETY: `accept` just returned the fd, nothing else owns it
+        Ok(LocalStream { fd: unsafe { OwnedFd::from_raw_fd(fd.0) } })
     }
 
     #[stable(feature = "rust1", since = "1.0.0")]
@@ -56,6 +59,17 @@ pub fn close(self) -> io::Result<()> {
         close(self.leak_fd()).map_err(map_sys_err)?;
         Ok(())
     }
+
+    /// Moves the socket into or out of non-blocking mode, see
+    /// [`BorrowedFd::set_nonblocking`].
+    #[stable(feature = "rust1", since = "1.0.0")]
+    pub fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        self.fd.as_fd().set_nonblocking(nonblocking)
+    }
+
+    fn fd(&self) -> FileDesc {
+        FileDesc(self.fd.as_raw_fd())
+    }
 }
 
 //
@@ -72,16 +86,19 @@ impl LocalStream {
     pub fn connect(addr: &str) -> io::Result<Self> {
         let fd = socket(SocketDomain::LOCAL, SocketType::STREAM, Protocol::LOCAL)
             .map_err(map_sys_err)?;
-        connect(fd, addr).map_err(map_sys_err)?;
+        // owned right away, so the socket is closed if `connect` fails
+        // SAFETY: `socket` just returned the fd, nothing else owns it
+        let this = Self { fd: unsafe { OwnedFd::from_raw_fd(fd.0) } };
+        connect(this.fd(), addr).map_err(map_sys_err)?;
 
-        Ok(Self { fd: OwnedFd(fd) })
+        Ok(this)
     }
 
     /// the file descriptor won't be closed automatically
     #[stable(feature = "rust1", since = "1.0.0")]
     #[must_use]
     pub fn leak_fd(self) -> FileDesc {
-        self.fd.leak_fd()
+        FileDesc(self.fd.into_raw_fd())
     }
 
     #[stable(feature = "rust1", since = "1.0.0")]
@@ -89,100 +106,64 @@ pub fn close(self) -> io::Result<()> {
         close(self.leak_fd()).map_err(map_sys_err)?;
         Ok(())
     }
+
+    /// Moves the socket into or out of non-blocking mode, see
+    /// [`BorrowedFd::set_nonblocking`].
+    #[stable(feature = "rust1", since = "1.0.0")]
+    pub fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        self.fd.as_fd().set_nonblocking(nonblocking)
+    }
+
+    fn fd(&self) -> FileDesc {
+        FileDesc(self.fd.as_raw_fd())
+    }
 }
 
 #[stable(feature = "rust1", since = "1.0.0")]
 impl Read for Arc<LocalStream> {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        (&self.fd).read(buf)
+        (&**self).read(buf)
     }
 }
 
 #[stable(feature = "rust1", since = "1.0.0")]
 impl Write for Arc<LocalStream> {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        (&self.fd).write(buf)
+        (&**self).write(buf)
     }
 
     fn flush(&mut self) -> io::Result<()> {
-        (&self.fd).flush()
+        (&**self).flush()
     }
 }
 
 #[stable(feature = "rust1", since = "1.0.0")]
 impl Read for &LocalStream {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        (&self.fd).read(buf)
+        recv(self.fd(), buf, 0).map_err(map_sys_err)
     }
 }
 
 #[stable(feature = "rust1", since = "1.0.0")]
 impl Write for &LocalStream {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        (&self.fd).write(buf)
+        send(self.fd(), buf, 0).map_err(map_sys_err)
     }
 
     fn flush(&mut self) -> io::Result<()> {
-        (&self.fd).flush()
+        Ok(())
     }
 }
 
 #[stable(feature = "rust1", since = "1.0.0")]
 impl Read for LocalStream {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        self.fd.read(buf)
+        (&*self).read(buf)
     }
 }
 
 #[stable(feature = "rust1", since = "1.0.0")]
 impl Write for LocalStream {
-    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        self.fd.write(buf)
-    }
-
-    fn flush(&mut self) -> io::Result<()> {
-        self.fd.flush()
-    }
-}
-
-//
-
-#[derive(Debug)]
-struct OwnedFd(FileDesc);
-
-impl OwnedFd {
-    fn leak_fd(self) -> FileDesc {
-        let fd = self.0;
-        forget(self);
-        fd
-    }
-}
-
-#[stable(feature = "rust1", since = "1.0.0")]
-impl Read for &OwnedFd {
-    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        recv(self.0, buf, 0).map_err(map_sys_err)
-    }
-}
-
-#[stable(feature = "rust1", since = "1.0.0")]
-impl Write for &OwnedFd {
-    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        send(self.0, buf, 0).map_err(map_sys_err)
-    }
-
-    fn flush(&mut self) -> io::Result<()> {
-        Ok(())
-    }
-}
-
-impl Read for OwnedFd {
-    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        (&*self).read(buf)
-    }
-}
-
-impl Write for OwnedFd {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
         (&*self).write(buf)
     }
@@ -192,8 +173,59 @@ fn flush(&mut self) -> io::Result<()> {
     }
 }
 
-impl Drop for OwnedFd {
-    fn drop(&mut self) {
-        close(self.0).unwrap()
-    }
-}
+//
+
+macro_rules! impl_fd_traits {
+    ($($t:ident),* $(,)?) => {$(
+        #[stable(feature = "rust1", since = "1.0.0")]
+        impl AsRawFd for $t {
+            #[inline]
+            fn as_raw_fd(&self) -> RawFd {
+                self.fd.as_raw_fd()
+            }
+        }
+
+        #[stable(feature = "rust1", since = "1.0.0")]
+        impl AsFd for $t {
+            #[inline]
+            fn as_fd(&self) -> BorrowedFd<'_> {
+                self.fd.as_fd()
+            }
+        }
+
+        #[stable(feature = "rust1", since = "1.0.0")]
+        impl IntoRawFd for $t {
+            #[inline]
+            fn into_raw_fd(self) -> RawFd {
+                self.fd.into_raw_fd()
+            }
+        }
+
+        #[stable(feature = "rust1", since = "1.0.0")]
+        impl FromRawFd for $t {
+            #[inline]
+            unsafe fn from_raw_fd(fd: RawFd) -> Self {
+                // SAFETY: the caller guarantees that `fd` is an owned local socket
+                Self { fd: unsafe { OwnedFd::from_raw_fd(fd) } }
+            }
+        }
+
+        #[stable(feature = "rust1", since = "1.0.0")]
+        impl From<$t> for OwnedFd {
+            #[inline]
+            fn from(socket: $t) -> OwnedFd {
+                socket.fd
+            }
+        }
+
+        #[stable(feature = "rust1", since = "1.0.0")]
+        impl From<OwnedFd> for $t {
+            #[inline]
+            fn from(fd: OwnedFd) -> Self {
+                Self { fd }
+            }
+        }
+    )*};
+}
+
+impl_fd_traits!(LocalListener, LocalStream);
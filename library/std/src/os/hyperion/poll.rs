@@ -0,0 +1,191 @@
+//! Waiting for file descriptors to become ready.
+//!
+//! Together with [`BorrowedFd::set_nonblocking`], [`poll`] is the building
+//! block for event loops: descriptors are switched to non-blocking mode, and
+//! whenever an operation fails with [`io::ErrorKind::WouldBlock`], the loop
+//! polls for the descriptor to become ready again.
+//!
+//! ```no_run
+//! use std::io::{self, Read};
+//! use std::os::hyperion::io::AsFd;
+//! use std::os::hyperion::net::LocalStream;
+//! use std::os::hyperion::poll::{poll, Events, PollFd};
+//!
+//! let mut stream = LocalStream::connect("/run/echo")?;
+//! stream.set_nonblocking(true)?;
+//!
+//! let mut buf = [0; 64];
+//! let n = loop {
+//!     match stream.read(&mut buf) {
+//!         Err(err) if err.kind() == io::ErrorKind::WouldBlock => {
+//!             poll(&mut [PollFd::new(stream.as_fd(), Events::READABLE)], None)?;
+//!         }
+//!         res => break res?,
+//!     }
+//! };
+//! println!("{:?}", &buf[..n]);
+//! # Ok::<(), io::Error>(())
+//! ```
+
+#![stable(feature = "rust1", since = "1.0.0")]
+#![deny(unsafe_op_in_unsafe_fn)]
+
+use hyperion_abi::sys::fs::{FileDesc, PollEvents, PollFd as RawPollFd};
+
+use crate::fmt;
+use crate::io;
+use crate::marker::PhantomData;
+use crate::ops;
+use crate::os::hyperion::io::{AsRawFd, BorrowedFd};
+use crate::os::hyperion::map_sys_err;
+use crate::time::Duration;
+
+//
+
+/// A set of readiness events.
+#[stable(feature = "rust1", since = "1.0.0")]
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Events(PollEvents);
+
+impl Events {
+    /// Reading won't block.
+    #[stable(feature = "rust1", since = "1.0.0")]
+    pub const READABLE: Self = Self(PollEvents::READ);
+    /// Writing won't block.
+    #[stable(feature = "rust1", since = "1.0.0")]
+    pub const WRITABLE: Self = Self(PollEvents::WRITE);
+    /// An error is pending on the descriptor.
+    ///
+    /// Always reported, there is no need to wait for it.
+    #[stable(feature = "rust1", since = "1.0.0")]
+    pub const ERROR: Self = Self(PollEvents::ERROR);
+    /// The other end was closed.
+    ///
+    /// Always reported, there is no need to wait for it.
+    #[stable(feature = "rust1", since = "1.0.0")]
+    pub const HANGUP: Self = Self(PollEvents::HANGUP);
+
+    /// No events.
+    #[stable(feature = "rust1", since = "1.0.0")]
+    pub const fn empty() -> Self {
+        Self(PollEvents::empty())
+    }
+
+    #[stable(feature = "rust1", since = "1.0.0")]
+    pub const fn is_empty(self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Returns `true` if all events in `other` are also in `self`.
+    #[stable(feature = "rust1", since = "1.0.0")]
+    pub const fn contains(self, other: Self) -> bool {
+        self.0.contains(other.0)
+    }
+}
+
+#[stable(feature = "rust1", since = "1.0.0")]
+impl ops::BitOr for Events {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+#[stable(feature = "rust1", since = "1.0.0")]
+impl ops::BitOrAssign for Events {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+#[stable(feature = "rust1", since = "1.0.0")]
+impl fmt::Debug for Events {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names = [
+            (Self::READABLE, "READABLE"),
+            (Self::WRITABLE, "WRITABLE"),
+            (Self::ERROR, "ERROR"),
+            (Self::HANGUP, "HANGUP"),
+        ];
+        let mut set = f.debug_set();
+        for (event, name) in names {
+            if self.contains(event) {
+                set.entry(&format_args!("{name}"));
+            }
+        }
+        set.finish()
+    }
+}
+
+//
+
+/// A file descriptor and the events to wait for on it.
+#[repr(transparent)]
+#[stable(feature = "rust1", since = "1.0.0")]
+pub struct PollFd<'fd> {
+    raw: RawPollFd,
+    _fd: PhantomData<BorrowedFd<'fd>>,
+}
+
+impl<'fd> PollFd<'fd> {
+    /// Waits for `events` on `fd`.
+    #[stable(feature = "rust1", since = "1.0.0")]
+    pub fn new(fd: BorrowedFd<'fd>, events: Events) -> Self {
+        let raw = RawPollFd {
+            fd: FileDesc(fd.as_raw_fd()),
+            events: events.0,
+            revents: PollEvents::empty(),
+        };
+        Self { raw, _fd: PhantomData }
+    }
+
+    #[stable(feature = "rust1", since = "1.0.0")]
+    pub fn fd(&self) -> BorrowedFd<'fd> {
+        // SAFETY: the descriptor is borrowed for `'fd`
+        unsafe { BorrowedFd::borrow_raw(self.raw.fd.0) }
+    }
+
+    /// The events to wait for.
+    #[stable(feature = "rust1", since = "1.0.0")]
+    pub fn events(&self) -> Events {
+        Events(self.raw.events)
+    }
+
+    #[stable(feature = "rust1", since = "1.0.0")]
+    pub fn set_events(&mut self, events: Events) {
+        self.raw.events = events.0;
+    }
+
+    /// The events the last [`poll`] found, empty before the first one.
+    #[stable(feature = "rust1", since = "1.0.0")]
+    pub fn revents(&self) -> Events {
+        Events(self.raw.revents)
+    }
+}
+
+#[stable(feature = "rust1", since = "1.0.0")]
+impl fmt::Debug for PollFd<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PollFd")
+            .field("fd", &self.raw.fd.0)
+            .field("events", &self.events())
+            .field("revents", &self.revents())
+            .finish()
+    }
+}
+
+//
+
+/// Waits until at least one of `fds` is ready, or until `timeout` passed.
+///
+/// `None` waits without a timeout, a zero timeout only checks the
+/// descriptors. Returns the number of descriptors with events, `0` if the
+/// timeout passed first. The events found are in [`PollFd::revents`].
+#[stable(feature = "rust1", since = "1.0.0")]
+pub fn poll(fds: &mut [PollFd<'_>], timeout: Option<Duration>) -> io::Result<usize> {
+    let timeout = timeout.map(|timeout| timeout.as_nanos().try_into().unwrap_or(u64::MAX));
+    // SAFETY: `PollFd` is a transparent wrapper around the kernel's type
+    let raw = unsafe { &mut *(fds as *mut [PollFd<'_>] as *mut [RawPollFd]) };
+    hyperion_abi::sys::poll(raw, timeout).map_err(map_sys_err)
+}
@@ -1,25 +1,113 @@
 use hyperion_abi::alloc::{PageAlloc, Pages, SlabAllocator};
-use hyperion_abi::sys::{palloc, pfree};
+use hyperion_abi::sys::{palloc, pfree, prealloc};
 
 use crate::alloc::{GlobalAlloc, Layout, System};
-use crate::ptr::NonNull;
+use crate::ptr::{self, NonNull};
+use crate::sys::common::alloc::realloc_fallback;
 
 //
 
 static SLAB: SlabAllocator<BaseAlloc> = SlabAllocator::new();
 
+pub(crate) const PAGE_SIZE: usize = 0x1000;
+
 //
 
 struct BaseAlloc;
 
 unsafe impl PageAlloc for BaseAlloc {
     unsafe fn alloc(pages: usize) -> Pages {
-        let alloc = palloc(pages).unwrap().unwrap();
-        unsafe { Pages::new(alloc.as_ptr(), pages) }
+        // `SlabAllocator` hands null back to its caller if it gets no pages
+        match palloc(pages) {
+            Ok(Some(alloc)) => unsafe { Pages::new(alloc.as_ptr(), pages) },
+            _ => unsafe { Pages::new(ptr::null_mut(), 0) },
+        }
     }
 
     unsafe fn dealloc(frames: Pages) {
-        pfree(NonNull::new(frames.as_ptr()).unwrap(), frames.len()).unwrap();
+        if let Some(alloc) = NonNull::new(frames.as_ptr()) {
+            free(alloc, frames.len());
+        }
+    }
+}
+
+//
+
+/// Allocations of at least a page bypass the slab allocator and map their own pages.
+fn is_large(layout: Layout) -> bool {
+    layout.size().max(layout.align()) >= PAGE_SIZE
+}
+
+fn pages_for(size: usize) -> usize {
+    size.div_ceil(PAGE_SIZE)
+}
+
+/// The allocator must not panic, and pages that can't be unmapped are only leaked.
+fn free(alloc: NonNull<u8>, pages: usize) {
+    _ = pfree(alloc, pages);
+}
+
+/// Fresh pages are always zeroed by the kernel.
+unsafe fn alloc_pages(layout: Layout) -> *mut u8 {
+    let pages = pages_for(layout.size());
+    if layout.align() <= PAGE_SIZE {
+        return match palloc(pages) {
+            Ok(Some(alloc)) => alloc.as_ptr(),
+            _ => ptr::null_mut(),
+        };
+    }
+
+    // pages are only page aligned, so this maps enough extra pages to find an
+    // aligned range in them and unmaps the rest again, which leaves exactly
+    // `pages` mapped for `dealloc_pages` and `realloc_pages`
+    let extra = layout.align() / PAGE_SIZE - 1;
+    let Ok(Some(alloc)) = palloc(pages + extra) else {
+        return ptr::null_mut();
+    };
+    let head = alloc.as_ptr().align_offset(layout.align()) / PAGE_SIZE;
+    let tail = extra - head;
+    // SAFETY: the head and tail pages are part of the allocation
+    unsafe {
+        if head != 0 {
+            free(alloc, head);
+        }
+        let ptr = alloc.as_ptr().add(head * PAGE_SIZE);
+        if tail != 0 {
+            free(NonNull::new_unchecked(ptr.add(pages * PAGE_SIZE)), tail);
+        }
+        ptr
+    }
+}
+
+unsafe fn dealloc_pages(ptr: *mut u8, layout: Layout) {
+    // SAFETY: `ptr` was allocated by `alloc_pages` with the same layout
+    let ptr = unsafe { NonNull::new_unchecked(ptr) };
+    free(ptr, pages_for(layout.size()));
+}
+
+/// Resizes a large allocation in whole pages, the kernel moves the mapping
+/// instead of copying the data if it can't grow in place.
+unsafe fn realloc_pages(ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+    let old_pages = pages_for(layout.size());
+    let new_pages = pages_for(new_size);
+
+    // SAFETY: `ptr` was allocated by `alloc_pages`
+    let alloc = unsafe { NonNull::new_unchecked(ptr) };
+    if new_pages == old_pages {
+        ptr
+    } else if new_pages < old_pages {
+        // SAFETY: the tail pages are still part of the allocation
+        let tail = unsafe { NonNull::new_unchecked(ptr.add(new_pages * PAGE_SIZE)) };
+        free(tail, old_pages - new_pages);
+        ptr
+    } else if layout.align() > PAGE_SIZE {
+        // the kernel might move the mapping to an address without the alignment
+        unsafe { realloc_fallback(&System, ptr, layout, new_size) }
+    } else {
+        match prealloc(alloc, old_pages, new_pages) {
+            Ok(Some(alloc)) => alloc.as_ptr(),
+            _ => ptr::null_mut(),
+        }
     }
 }
 
@@ -29,11 +117,40 @@ unsafe fn dealloc(frames: Pages) {
 unsafe impl GlobalAlloc for System {
     #[inline]
     unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
-        unsafe { GlobalAlloc::alloc(&SLAB, layout) }
+        if is_large(layout) {
+            unsafe { alloc_pages(layout) }
+        } else {
+            unsafe { GlobalAlloc::alloc(&SLAB, layout) }
+        }
+    }
+
+    #[inline]
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        if is_large(layout) {
+            unsafe { alloc_pages(layout) }
+        } else {
+            unsafe { GlobalAlloc::alloc_zeroed(&SLAB, layout) }
+        }
     }
 
     #[inline]
     unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
-        unsafe { GlobalAlloc::dealloc(&SLAB, ptr, layout) }
+        if is_large(layout) {
+            unsafe { dealloc_pages(ptr, layout) }
+        } else {
+            unsafe { GlobalAlloc::dealloc(&SLAB, ptr, layout) }
+        }
+    }
+
+    #[inline]
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        // SAFETY: the caller guarantees that `new_size` is a valid size for `layout.align()`
+        let new_layout = unsafe { Layout::from_size_align_unchecked(new_size, layout.align()) };
+        match (is_large(layout), is_large(new_layout)) {
+            (true, true) => unsafe { realloc_pages(ptr, layout, new_size) },
+            (false, false) => unsafe { GlobalAlloc::realloc(&SLAB, ptr, layout, new_size) },
+            // moving between the slab allocator and whole pages always copies
+            _ => unsafe { realloc_fallback(self, ptr, layout, new_size) },
+        }
     }
 }
@@ -66,13 +66,14 @@ pub(crate) unsafe fn init_args(hyperion_cli_args_ptr: usize) {
 
 static mut ARGS: &[&str] = &[];
 
+/// the kernel passes both cli args and env vars in this same format
 #[derive(Clone, Copy)]
-struct CliArgs {
-    hyperion_cli_args_ptr: usize,
+pub(super) struct CliArgs {
+    pub(super) hyperion_cli_args_ptr: usize,
 }
 
 impl CliArgs {
-    fn iter(self) -> impl DoubleEndedIterator<Item = &'static str> + Clone {
+    pub(super) fn iter(self) -> impl DoubleEndedIterator<Item = &'static str> + Clone {
         let mut ptr = self.hyperion_cli_args_ptr;
 
         let argc: usize = Self::pop(&mut ptr);
@@ -0,0 +1,82 @@
+//! Stack traces for `std::backtrace` and panics.
+//!
+//! backtrace-rs doesn't know about Hyperion, so this provides the part of its
+//! API that std uses in its place. Frames are walked with the same DWARF
+//! unwinder that implements `panic=unwind`, and symbolized with the
+//! executable's own symbol table and DWARF.
+
+use unwind as uw;
+
+use crate::ffi::c_void;
+use crate::fmt;
+use crate::ptr;
+
+mod print;
+mod symbolize;
+
+pub use self::print::{BacktraceFmt, BacktraceFrameFmt, PrintFmt};
+pub use self::symbolize::{resolve_frame_unsynchronized, BytesOrWideString, Symbol, SymbolName};
+
+//
+
+/// A frame of a stack trace.
+#[derive(Clone)]
+pub struct Frame {
+    ip: usize,
+    symbol_address: usize,
+}
+
+impl Frame {
+    /// The return address of the frame, the current instruction for the
+    /// innermost one.
+    pub fn ip(&self) -> *mut c_void {
+        ptr::invalid_mut(self.ip)
+    }
+
+    /// The start of the function the frame is in.
+    pub fn symbol_address(&self) -> *mut c_void {
+        ptr::invalid_mut(self.symbol_address)
+    }
+}
+
+impl fmt::Debug for Frame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Frame")
+            .field("ip", &self.ip())
+            .field("symbol_address", &self.symbol_address())
+            .finish()
+    }
+}
+
+/// Calls `cb` with every frame of the current stack, innermost first, until
+/// it returns `false`.
+///
+/// # Safety
+///
+/// Has to be called with std's backtrace lock held.
+pub unsafe fn trace_unsynchronized<F: FnMut(&Frame) -> bool>(mut cb: F) {
+    trace(&mut cb)
+}
+
+#[inline(never)]
+fn trace(mut cb: &mut dyn FnMut(&Frame) -> bool) {
+    extern "C" fn trace_fn(
+        ctx: *mut uw::_Unwind_Context,
+        arg: *mut c_void,
+    ) -> uw::_Unwind_Reason_Code {
+        // SAFETY: `arg` is the `cb` passed to `_Unwind_Backtrace` below, and
+        // `ctx` is valid during the call
+        let (cb, frame) = unsafe {
+            let cb = &mut *(arg as *mut &mut dyn FnMut(&Frame) -> bool);
+            let frame = Frame {
+                ip: uw::_Unwind_GetIP(ctx).addr(),
+                symbol_address: uw::_Unwind_GetRegionStart(ctx).addr(),
+            };
+            (cb, frame)
+        };
+        if cb(&frame) { uw::_URC_NO_REASON } else { uw::_URC_FAILURE }
+    }
+
+    // SAFETY: `trace_fn` gets `cb` back as its argument
+    unsafe { uw::_Unwind_Backtrace(trace_fn, &mut cb as *mut _ as *mut c_void) };
+}
@@ -0,0 +1,144 @@
+//! Printing stack traces in the same format as backtrace-rs.
+
+use super::{BytesOrWideString, Frame, Symbol, SymbolName};
+use crate::ffi::c_void;
+use crate::fmt;
+use crate::mem;
+
+//
+
+const HEX_WIDTH: usize = 2 + 2 * mem::size_of::<usize>();
+
+/// How much of a stack trace to print.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PrintFmt {
+    /// Demangled names without hashes, no addresses.
+    Short,
+    /// Everything that is known about every frame.
+    Full,
+}
+
+/// Prints a stack trace frame by frame.
+pub struct BacktraceFmt<'a, 'b> {
+    fmt: &'a mut fmt::Formatter<'b>,
+    frame_index: usize,
+    format: PrintFmt,
+    print_path:
+        &'a mut (dyn FnMut(&mut fmt::Formatter<'_>, BytesOrWideString<'_>) -> fmt::Result + 'b),
+}
+
+impl<'a, 'b> BacktraceFmt<'a, 'b> {
+    /// `print_path` prints the source file names.
+    pub fn new(
+        fmt: &'a mut fmt::Formatter<'b>,
+        format: PrintFmt,
+        print_path: &'a mut (
+                    dyn FnMut(&mut fmt::Formatter<'_>, BytesOrWideString<'_>) -> fmt::Result + 'b
+                ),
+    ) -> Self {
+        BacktraceFmt { fmt, frame_index: 0, format, print_path }
+    }
+
+    /// Prints what has to come before the first frame, nothing on Hyperion.
+    pub fn add_context(&mut self) -> fmt::Result {
+        Ok(())
+    }
+
+    /// Prints what has to come after the last frame, nothing on Hyperion.
+    pub fn finish(&mut self) -> fmt::Result {
+        Ok(())
+    }
+
+    /// Starts the next frame, which ends when the returned value is dropped.
+    pub fn frame(&mut self) -> BacktraceFrameFmt<'_, 'a, 'b> {
+        BacktraceFrameFmt { fmt: self, symbol_index: 0 }
+    }
+
+    pub fn formatter(&mut self) -> &mut fmt::Formatter<'b> {
+        self.fmt
+    }
+}
+
+/// Prints the symbols of a frame, several if it's in inlined code.
+pub struct BacktraceFrameFmt<'fmt, 'a, 'b> {
+    fmt: &'fmt mut BacktraceFmt<'a, 'b>,
+    symbol_index: usize,
+}
+
+impl BacktraceFrameFmt<'_, '_, '_> {
+    pub fn symbol(&mut self, frame: &Frame, symbol: &Symbol<'_>) -> fmt::Result {
+        self.print_raw_with_column(
+            frame.ip(),
+            symbol.name(),
+            symbol.filename_raw(),
+            symbol.lineno(),
+            symbol.colno(),
+        )
+    }
+
+    pub fn print_raw(
+        &mut self,
+        frame_ip: *mut c_void,
+        symbol_name: Option<SymbolName<'_>>,
+        filename: Option<BytesOrWideString<'_>>,
+        lineno: Option<u32>,
+    ) -> fmt::Result {
+        self.print_raw_with_column(frame_ip, symbol_name, filename, lineno, None)
+    }
+
+    pub fn print_raw_with_column(
+        &mut self,
+        frame_ip: *mut c_void,
+        symbol_name: Option<SymbolName<'_>>,
+        filename: Option<BytesOrWideString<'_>>,
+        lineno: Option<u32>,
+        colno: Option<u32>,
+    ) -> fmt::Result {
+        // null frames only mean that the unwinder went a bit too far
+        if self.fmt.format == PrintFmt::Short && frame_ip.is_null() {
+            return Ok(());
+        }
+
+        // the index and address only go in front of the first symbol
+        if self.symbol_index == 0 {
+            write!(self.fmt.fmt, "{:4}: ", self.fmt.frame_index)?;
+            if self.fmt.format == PrintFmt::Full {
+                write!(self.fmt.fmt, "{:1$?} - ", frame_ip, HEX_WIDTH)?;
+            }
+        } else {
+            write!(self.fmt.fmt, "      ")?;
+            if self.fmt.format == PrintFmt::Full {
+                write!(self.fmt.fmt, "{:1$}", "", HEX_WIDTH + 3)?;
+            }
+        }
+
+        match (symbol_name, self.fmt.format) {
+            (Some(name), PrintFmt::Short) => write!(self.fmt.fmt, "{name:#}")?,
+            (Some(name), PrintFmt::Full) => write!(self.fmt.fmt, "{name}")?,
+            (None, _) => write!(self.fmt.fmt, "<unknown>")?,
+        }
+        writeln!(self.fmt.fmt)?;
+
+        if let (Some(file), Some(line)) = (filename, lineno) {
+            if self.fmt.format == PrintFmt::Full {
+                write!(self.fmt.fmt, "{:1$}", "", HEX_WIDTH)?;
+            }
+            write!(self.fmt.fmt, "             at ")?;
+            (self.fmt.print_path)(self.fmt.fmt, file)?;
+            write!(self.fmt.fmt, ":{line}")?;
+            if let Some(colno) = colno {
+                write!(self.fmt.fmt, ":{colno}")?;
+            }
+            writeln!(self.fmt.fmt)?;
+        }
+
+        self.symbol_index += 1;
+        Ok(())
+    }
+}
+
+impl Drop for BacktraceFrameFmt<'_, '_, '_> {
+    fn drop(&mut self) {
+        self.fmt.frame_index += 1;
+    }
+}
@@ -0,0 +1,206 @@
+//! Symbolization with the executable's own symbol table and DWARF.
+//!
+//! The executable is read from [`current_exe`] the first time a frame is
+//! resolved, and kept for the rest of the process.
+//!
+//! [`current_exe`]: crate::env::current_exe
+
+use super::Frame;
+use crate::fmt;
+use crate::str;
+
+//
+
+/// A source file name.
+pub enum BytesOrWideString<'a> {
+    Bytes(&'a [u8]),
+    /// Only Windows has these, std matches on them everywhere.
+    #[allow(dead_code)]
+    Wide(&'a [u16]),
+}
+
+/// What is known about the code a frame is in.
+pub struct Symbol<'a> {
+    name: Option<&'a [u8]>,
+    file: Option<&'a str>,
+    line: Option<u32>,
+    column: Option<u32>,
+}
+
+impl Symbol<'_> {
+    pub fn name(&self) -> Option<SymbolName<'_>> {
+        self.name.map(SymbolName::new)
+    }
+
+    pub fn filename_raw(&self) -> Option<BytesOrWideString<'_>> {
+        self.file.map(|file| BytesOrWideString::Bytes(file.as_bytes()))
+    }
+
+    pub fn lineno(&self) -> Option<u32> {
+        self.line
+    }
+
+    pub fn colno(&self) -> Option<u32> {
+        self.column
+    }
+}
+
+/// A possibly mangled function name.
+pub struct SymbolName<'a> {
+    bytes: &'a [u8],
+    demangled: Option<rustc_demangle::Demangle<'a>>,
+}
+
+impl<'a> SymbolName<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        let demangled =
+            str::from_utf8(bytes).ok().and_then(|name| rustc_demangle::try_demangle(name).ok());
+        SymbolName { bytes, demangled }
+    }
+
+    /// The name as it is in the binary, if it's UTF-8.
+    pub fn as_str(&self) -> Option<&'a str> {
+        match &self.demangled {
+            Some(demangled) => Some(demangled.as_str()),
+            None => str::from_utf8(self.bytes).ok(),
+        }
+    }
+
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+}
+
+/// Demangles Rust names, `{:#}` leaves out the hash.
+impl fmt::Display for SymbolName<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.demangled {
+            Some(demangled) => fmt::Display::fmt(demangled, f),
+            None => fmt::Display::fmt(&String::from_utf8_lossy(self.bytes), f),
+        }
+    }
+}
+
+impl fmt::Debug for SymbolName<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.demangled {
+            Some(demangled) => fmt::Debug::fmt(demangled, f),
+            None => fmt::Debug::fmt(&String::from_utf8_lossy(self.bytes), f),
+        }
+    }
+}
+
+/// Calls `cb` with the symbols of the code `frame` is in, the innermost first
+/// if it's in inlined code.
+///
+/// # Safety
+///
+/// Has to be called with std's backtrace lock held.
+pub unsafe fn resolve_frame_unsynchronized<F: FnMut(&Symbol<'_>)>(frame: &Frame, mut cb: F) {
+    // return addresses point after the call, which might already be on the
+    // next line or even in the next function
+    let ip = if frame.ip == 0 { 0 } else { frame.ip - 1 };
+    imp::resolve(ip, &mut cb)
+}
+
+#[cfg(all(feature = "backtrace", not(test)))]
+mod imp {
+    use addr2line::gimli::{self, EndianSlice, NativeEndian};
+    use object::{Object, ObjectSection, ObjectSymbol, SymbolKind};
+
+    use super::super::super::elf;
+    use super::Symbol;
+    use crate::sync::{Mutex, PoisonError};
+    use crate::{env, fs};
+
+    type Reader = EndianSlice<'static, NativeEndian>;
+
+    struct Symbolizer {
+        /// How far the executable was moved from its link address.
+        bias: usize,
+        dwarf: Option<addr2line::Context<Reader>>,
+        /// The functions in the symbol table as `(address, size, name)`,
+        /// sorted by address, for executables without DWARF.
+        functions: Vec<(u64, u64, &'static [u8])>,
+    }
+
+    pub(super) fn resolve(ip: usize, cb: &mut dyn FnMut(&Symbol<'_>)) {
+        // `None` until the executable was read, `Some(None)` if that failed
+        static SYMBOLIZER: Mutex<Option<Option<Symbolizer>>> = Mutex::new(None);
+
+        let mut symbolizer = SYMBOLIZER.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(symbolizer) = symbolizer.get_or_insert_with(Symbolizer::load) {
+            symbolizer.resolve(ip, cb);
+        }
+    }
+
+    impl Symbolizer {
+        fn load() -> Option<Self> {
+            // symbol names and file names borrow from the file for the rest
+            // of the process
+            let data: &'static [u8] = fs::read(env::current_exe().ok()?).ok()?.leak();
+            let object = object::File::parse(data).ok()?;
+
+            let section = |id: gimli::SectionId| -> Result<Reader, ()> {
+                let data = object.section_by_name(id.name()).and_then(|s| s.data().ok());
+                Ok(EndianSlice::new(data.unwrap_or(&[]), NativeEndian))
+            };
+            let dwarf = gimli::Dwarf::load(section).ok();
+            let dwarf = dwarf.and_then(|dwarf| addr2line::Context::from_dwarf(dwarf).ok());
+
+            let mut functions: Vec<_> = object
+                .symbols()
+                .filter(|sym| sym.kind() == SymbolKind::Text && sym.is_definition())
+                .filter_map(|sym| Some((sym.address(), sym.size(), sym.name_bytes().ok()?)))
+                .collect();
+            functions.sort_unstable_by_key(|&(addr, ..)| addr);
+
+            Some(Symbolizer { bias: elf::load_bias()?, dwarf, functions })
+        }
+
+        fn resolve(&self, ip: usize, cb: &mut dyn FnMut(&Symbol<'_>)) {
+            // DWARF and the symbol table use link addresses
+            let probe = ip.wrapping_sub(self.bias) as u64;
+
+            let mut found = false;
+            if let Some(Ok(mut frames)) =
+                self.dwarf.as_ref().map(|dwarf| dwarf.find_frames(probe).skip_all_loads())
+            {
+                while let Ok(Some(frame)) = frames.next() {
+                    found = true;
+                    let location = frame.location.as_ref();
+                    cb(&Symbol {
+                        name: match &frame.function {
+                            Some(function) => Some(function.name.slice()),
+                            None => self.function(probe),
+                        },
+                        file: location.and_then(|location| location.file),
+                        line: location.and_then(|location| location.line),
+                        column: location.and_then(|location| location.column),
+                    });
+                }
+            }
+
+            if !found {
+                if let Some(name) = self.function(probe) {
+                    cb(&Symbol { name: Some(name), file: None, line: None, column: None });
+                }
+            }
+        }
+
+        /// Finds the name of the function containing `probe` in the symbol table.
+        fn function(&self, probe: u64) -> Option<&'static [u8]> {
+            let i = self.functions.partition_point(|&(addr, ..)| addr <= probe).checked_sub(1)?;
+            let (addr, size, name) = self.functions[i];
+            (size == 0 || probe < addr + size).then_some(name)
+        }
+    }
+}
+
+// std's own tests never print backtraces
+#[cfg(not(all(feature = "backtrace", not(test))))]
+mod imp {
+    use super::Symbol;
+
+    pub(super) fn resolve(_ip: usize, _cb: &mut dyn FnMut(&Symbol<'_>)) {}
+}
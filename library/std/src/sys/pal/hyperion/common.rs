@@ -1,6 +1,9 @@
 use hyperion_abi::sys::err::Error;
 
-use crate::{io as std_io, os::hyperion::sys_err_kind};
+use crate::{
+    io as std_io,
+    os::hyperion::{sys_err_kind, to_sys_err},
+};
 
 //
 
@@ -10,7 +13,9 @@ pub mod memchr {
 
 // SAFETY: must be called only once during runtime initialization.
 // NOTE: this is not guaranteed to run, for example when Rust code is called externally.
-pub unsafe fn init(_argc: isize, _argv: *const *const u8, _sigpipe: u8) {}
+pub unsafe fn init(_argc: isize, _argv: *const *const u8, _sigpipe: u8) {
+    unsafe { super::stack_overflow::init() };
+}
 
 // SAFETY: must be called only once during runtime cleanup.
 // NOTE: this is not guaranteed to run, for example when the program aborts.
@@ -27,8 +32,8 @@ pub fn unsupported_err() -> std_io::Error {
     )
 }
 
-pub fn is_interrupted(_code: i32) -> bool {
-    false
+pub fn is_interrupted(code: i32) -> bool {
+    to_sys_err(code) == Error::INTERRUPTED
 }
 
 pub fn decode_error_kind(_code: i32) -> crate::io::ErrorKind {
@@ -40,5 +45,10 @@ pub fn abort_internal() -> ! {
 }
 
 pub fn hashmap_random_keys() -> (u64, u64) {
-    (1, 2)
+    let mut keys = [0u8; 16];
+    if let Err(err) = super::rand::fill_bytes(&mut keys) {
+        rtabort!("failed to obtain random data: {err}");
+    }
+    let (k0, k1) = keys.split_at(8);
+    (u64::from_ne_bytes(k0.try_into().unwrap()), u64::from_ne_bytes(k1.try_into().unwrap()))
 }
@@ -0,0 +1,31 @@
+//! Lets the unwinder find the `.eh_frame_hdr` of statically linked binaries.
+
+use super::elf::{self, PT_GNU_EH_FRAME};
+use crate::sync::atomic::{AtomicUsize, Ordering};
+
+//
+
+pub(crate) struct EhFrameFinder(AtomicUsize /* eh_frame_hdr */);
+
+pub(crate) static EH_FRAME_FINDER: EhFrameFinder = EhFrameFinder(AtomicUsize::new(0));
+
+impl EhFrameFinder {
+    pub(crate) fn init(&self) {
+        let Some(bias) = elf::load_bias() else { return };
+        if let Some(phdr) = elf::phdrs().iter().find(|phdr| phdr.ty == PT_GNU_EH_FRAME) {
+            self.0.store(phdr.vaddr.wrapping_add(bias), Ordering::Release);
+        }
+    }
+}
+
+unsafe impl unwind::EhFrameFinder for EhFrameFinder {
+    fn find(&self, _pc: usize) -> Option<unwind::FrameInfo> {
+        match self.0.load(Ordering::Acquire) {
+            0 => None,
+            eh_frame_hdr => Some(unwind::FrameInfo {
+                text_base: None,
+                kind: unwind::FrameInfoKind::EhFrameHdr(eh_frame_hdr),
+            }),
+        }
+    }
+}
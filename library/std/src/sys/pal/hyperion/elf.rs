@@ -0,0 +1,99 @@
+//! The binary's own ELF headers.
+//!
+//! There is no dynamic loader to ask with `dl_iterate_phdr`, so the program
+//! headers are read through the ELF header, which the linker maps at
+//! `__ehdr_start`.
+//!
+//! This also runs before the binary is relocated, so nothing in here may use
+//! a pointer from the binary's data.
+
+use crate::arch::asm;
+use crate::slice;
+
+//
+
+pub const PT_LOAD: u32 = 1;
+pub const PT_DYNAMIC: u32 = 2;
+pub const PT_GNU_EH_FRAME: u32 = 0x6474e550;
+
+/// `Elf64_Ehdr`
+#[allow(dead_code)] // only the program header table is used
+#[repr(C)]
+struct Ehdr {
+    ident: [u8; 16],
+    ty: u16,
+    machine: u16,
+    version: u32,
+    entry: usize,
+    phoff: usize,
+    shoff: usize,
+    flags: u32,
+    ehsize: u16,
+    phentsize: u16,
+    phnum: u16,
+    shentsize: u16,
+    shnum: u16,
+    shstrndx: u16,
+}
+
+/// `Elf64_Phdr`
+#[allow(dead_code)]
+#[repr(C)]
+pub struct Phdr {
+    pub ty: u32,
+    pub flags: u32,
+    pub offset: usize,
+    pub vaddr: usize,
+    pub paddr: usize,
+    pub filesz: usize,
+    pub memsz: usize,
+    pub align: usize,
+}
+
+//
+
+/// Returns the binary's program headers.
+pub fn phdrs() -> &'static [Phdr] {
+    // SAFETY: the ELF header and the program headers are mapped as part of
+    // the first `PT_LOAD` segment
+    unsafe {
+        let ehdr = &*ehdr_start();
+        slice::from_raw_parts((ehdr as *const Ehdr).byte_add(ehdr.phoff).cast(), ehdr.phnum as _)
+    }
+}
+
+/// Returns how far the binary was moved from its link address.
+pub fn load_bias() -> Option<usize> {
+    // the segment containing the ELF header tells the load bias
+    let load = phdrs().iter().find(|phdr| phdr.ty == PT_LOAD && phdr.offset == 0)?;
+    Some(ehdr_start().addr().wrapping_sub(load.vaddr))
+}
+
+/// Returns the runtime address of the ELF header.
+fn ehdr_start() -> *const Ehdr {
+    let ehdr: *const Ehdr;
+    // a PC-relative address works without relocations, a GOT entry wouldn't
+    // SAFETY: `__ehdr_start` is defined by the linker
+    unsafe {
+        #[cfg(target_arch = "x86_64")]
+        asm!(
+            "lea {}, [rip + __ehdr_start]",
+            out(reg) ehdr,
+            options(pure, nomem, nostack, preserves_flags),
+        );
+        #[cfg(target_arch = "aarch64")]
+        asm!(
+            "adrp {0}, __ehdr_start",
+            "add {0}, {0}, :lo12:__ehdr_start",
+            out(reg) ehdr,
+            options(pure, nomem, nostack, preserves_flags),
+        );
+        #[cfg(target_arch = "riscv64")]
+        asm!(
+            "lla {}, __ehdr_start",
+            out(reg) ehdr,
+            options(pure, nomem, nostack, preserves_flags),
+        );
+    }
+    ehdr
+}
@@ -1,15 +1,21 @@
-use hyperion_abi::sys::fs::{FileDesc, FileOpenFlags, Metadata};
-use hyperion_abi::sys::{close, metadata, open};
+use hyperion_abi::sys::err::Result as SysResult;
+use hyperion_abi::sys::fs::{DirEntry as RawDirEntry, FileDesc, FileKind, FileOpenFlags, Metadata};
+use hyperion_abi::sys::{close, metadata, open, read_dir, rename_path};
 
+use crate::borrow::Cow;
 use crate::ffi::OsString;
 use crate::fmt;
 use crate::hash::Hash;
 use crate::io::{self, BorrowedCursor, IoSlice, IoSliceMut, SeekFrom};
+use crate::mem;
 use crate::os::hyperion::map_sys_err;
-use crate::path::{Path, PathBuf};
+use crate::path::{Component, Path, PathBuf};
+use crate::sync::Arc;
+use crate::sys::io::{as_bufs, as_bufs_mut};
 use crate::sys::time::SystemTime;
-use crate::sys::unsupported;
-use crate::sys_common::AsInner;
+use crate::sys_common::{AsInner, IntoInner};
+
+pub use crate::sys_common::fs::{remove_dir_all, try_exists};
 
 //
 
@@ -18,9 +24,21 @@
 #[derive(Copy, Clone, Debug)]
 pub struct FileAttr(Metadata);
 
-pub struct ReadDir(!);
+// all DirEntry's will have a reference to this struct
+struct InnerReadDir {
+    dir: File,
+    root: PathBuf,
+}
 
-pub struct DirEntry(!);
+pub struct ReadDir {
+    inner: Arc<InnerReadDir>,
+}
+
+pub struct DirEntry {
+    name: String,
+    kind: FileKind,
+    inner: Arc<InnerReadDir>,
+}
 
 #[derive(Clone, Debug)]
 pub struct OpenOptions {
@@ -28,15 +46,21 @@ pub struct OpenOptions {
 }
 
 #[derive(Copy, Clone, Debug, Default)]
-pub struct FileTimes {}
+pub struct FileTimes {
+    accessed: Option<SystemTime>,
+    modified: Option<SystemTime>,
+}
 
-pub struct FilePermissions(!);
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct FilePermissions {
+    mode: u32,
+}
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum FileType {
     File,
-    // Dir,
-    // Symlink,
+    Dir,
+    Symlink,
 }
 
 #[derive(Debug)]
@@ -47,67 +71,79 @@ pub fn size(&self) -> u64 {
         self.0.len as _
     }
 
-    #[track_caller]
     pub fn perm(&self) -> FilePermissions {
-        Err(io::const_io_error!(io::ErrorKind::Unsupported, "perm unsupported")).unwrap()
+        FilePermissions { mode: self.0.mode }
     }
 
     pub fn file_type(&self) -> FileType {
-        FileType::File
+        FileType::from_kind(self.0.kind)
     }
 
     pub fn modified(&self) -> io::Result<SystemTime> {
-        Err(io::const_io_error!(io::ErrorKind::Unsupported, "modified unsupported")).unwrap()
+        Self::time(self.0.modified).ok_or(io::const_io_error!(
+            io::ErrorKind::Unsupported,
+            "modification time is not available on this filesystem"
+        ))
     }
 
     pub fn accessed(&self) -> io::Result<SystemTime> {
-        Err(io::const_io_error!(io::ErrorKind::Unsupported, "accessed unsupported")).unwrap()
+        Self::time(self.0.accessed).ok_or(io::const_io_error!(
+            io::ErrorKind::Unsupported,
+            "access time is not available on this filesystem"
+        ))
     }
 
     pub fn created(&self) -> io::Result<SystemTime> {
-        Err(io::const_io_error!(io::ErrorKind::Unsupported, "created unsupported")).unwrap()
+        Self::time(self.0.created).ok_or(io::const_io_error!(
+            io::ErrorKind::Unsupported,
+            "creation time is not available on this filesystem"
+        ))
+    }
+
+    /// the kernel reports timestamps the filesystem doesn't keep track of as `0`
+    fn time(nanos: u64) -> Option<SystemTime> {
+        (nanos != 0).then(|| SystemTime::from_nanos(nanos))
     }
 }
 
 impl FilePermissions {
     pub fn readonly(&self) -> bool {
-        self.0
+        // check if any class (owner, group, others) has write permission
+        self.mode & 0o222 == 0
     }
 
-    pub fn set_readonly(&mut self, _readonly: bool) {
-        self.0
+    pub fn set_readonly(&mut self, readonly: bool) {
+        if readonly {
+            // remove write permission for all classes; equivalent to `chmod a-w <file>`
+            self.mode &= !0o222;
+        } else {
+            // add write permission for all classes; equivalent to `chmod a+w <file>`
+            self.mode |= 0o222;
+        }
     }
 }
 
-impl Clone for FilePermissions {
-    fn clone(&self) -> FilePermissions {
-        self.0
+impl FileTimes {
+    pub fn set_accessed(&mut self, t: SystemTime) {
+        self.accessed = Some(t);
     }
-}
 
-impl PartialEq for FilePermissions {
-    fn eq(&self, _other: &FilePermissions) -> bool {
-        self.0
+    pub fn set_modified(&mut self, t: SystemTime) {
+        self.modified = Some(t);
     }
 }
 
-impl Eq for FilePermissions {}
-
-impl fmt::Debug for FilePermissions {
-    fn fmt(&self, _f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        self.0
+impl FileType {
+    fn from_kind(kind: FileKind) -> Self {
+        match kind {
+            FileKind::DIR => Self::Dir,
+            FileKind::SYMLINK => Self::Symlink,
+            _ => Self::File,
+        }
     }
-}
 
-impl FileTimes {
-    pub fn set_accessed(&mut self, _t: SystemTime) {}
-    pub fn set_modified(&mut self, _t: SystemTime) {}
-}
-
-impl FileType {
     pub fn is_dir(&self) -> bool {
-        // matches!(self, Self::Dir)
-        false
+        matches!(self, Self::Dir)
     }
 
     pub fn is_file(&self) -> bool {
@@ -115,14 +151,15 @@ pub fn is_file(&self) -> bool {
     }
 
     pub fn is_symlink(&self) -> bool {
-        // matches!(self, Self::Symlink)
-        false
+        matches!(self, Self::Symlink)
     }
 }
 
 impl fmt::Debug for ReadDir {
-    fn fmt(&self, _f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        self.0
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // This will only be called from std::fs::ReadDir, which will add a "ReadDir()" frame.
+        // Thus the result will be e g 'ReadDir("/home")'
+        fmt::Debug::fmt(&*self.inner.root, f)
     }
 }
 
@@ -130,25 +167,44 @@ impl Iterator for ReadDir {
     type Item = io::Result<DirEntry>;
 
     fn next(&mut self) -> Option<io::Result<DirEntry>> {
-        self.0
+        loop {
+            let mut entry = RawDirEntry::zeroed();
+            match read_dir(self.inner.dir.0, &mut entry) {
+                Ok(false) => return None,
+                Ok(true) => {}
+                Err(err) => return Some(Err(map_sys_err(err))),
+            }
+
+            // the kernel lists `.` and `..` too, std doesn't
+            let name = entry.name();
+            if name == "." || name == ".." {
+                continue;
+            }
+
+            return Some(Ok(DirEntry {
+                name: name.to_string(),
+                kind: entry.kind,
+                inner: Arc::clone(&self.inner),
+            }));
+        }
     }
 }
 
 impl DirEntry {
     pub fn path(&self) -> PathBuf {
-        self.0
+        self.inner.root.join(&self.name)
     }
 
     pub fn file_name(&self) -> OsString {
-        self.0
+        OsString::from(&self.name)
     }
 
     pub fn metadata(&self) -> io::Result<FileAttr> {
-        self.0
+        lstat(&self.path())
     }
 
     pub fn file_type(&self) -> io::Result<FileType> {
-        self.0
+        Ok(FileType::from_kind(self.kind))
     }
 }
 
@@ -207,15 +263,7 @@ pub fn open(path: &Path, opts: &OpenOptions) -> io::Result<File> {
             ));
         }
 
-        let path = path.canonicalize()?;
-        let Some(path) = path.to_str() else {
-            return Err(io::const_io_error!(
-                io::ErrorKind::InvalidFilename,
-                "the path should be UTF-8"
-            ));
-        };
-
-        open(path, opts.flags, 0).map(File).map_err(map_sys_err)
+        run_path_with_str(path, |path| open(path, opts.flags, 0).map(File).map_err(map_sys_err))
     }
 
     pub fn file_attr(&self) -> io::Result<FileAttr> {
@@ -225,27 +273,31 @@ pub fn file_attr(&self) -> io::Result<FileAttr> {
     }
 
     pub fn fsync(&self) -> io::Result<()> {
-        Err(io::const_io_error!(io::ErrorKind::Unsupported, "fsync unsupported"))
+        hyperion_abi::sys::fsync(self.0).map_err(map_sys_err)
     }
 
     pub fn datasync(&self) -> io::Result<()> {
-        Err(io::const_io_error!(io::ErrorKind::Unsupported, "datasync unsupported"))
+        // the kernel always writes the metadata back together with the data
+        self.fsync()
     }
 
-    pub fn truncate(&self, _size: u64) -> io::Result<()> {
-        Err(io::const_io_error!(io::ErrorKind::Unsupported, "truncate unsupported"))
+    pub fn truncate(&self, size: u64) -> io::Result<()> {
+        let size = usize::try_from(size).map_err(|_| {
+            io::const_io_error!(io::ErrorKind::InvalidInput, "the file size is too large")
+        })?;
+        hyperion_abi::sys::truncate(self.0, size).map_err(map_sys_err)
     }
 
     pub fn read(&self, buf: &mut [u8]) -> io::Result<usize> {
         hyperion_abi::sys::read(self.0, buf).map_err(map_sys_err)
     }
 
-    pub fn read_vectored(&self, _bufs: &mut [IoSliceMut<'_>]) -> io::Result<usize> {
-        Err(io::const_io_error!(io::ErrorKind::Unsupported, "read_vectored unsupported"))
+    pub fn read_vectored(&self, bufs: &mut [IoSliceMut<'_>]) -> io::Result<usize> {
+        hyperion_abi::sys::readv(self.0, as_bufs_mut(bufs)).map_err(map_sys_err)
     }
 
     pub fn is_read_vectored(&self) -> bool {
-        false
+        true
     }
 
     pub fn read_buf(&self, mut cursor: BorrowedCursor<'_>) -> io::Result<()> {
@@ -255,16 +307,30 @@ pub fn read_buf(&self, mut cursor: BorrowedCursor<'_>) -> io::Result<()> {
         Ok(())
     }
 
+    pub fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        hyperion_abi::sys::pread(self.0, buf, Self::offset(offset)?).map_err(map_sys_err)
+    }
+
     pub fn write(&self, buf: &[u8]) -> io::Result<usize> {
         hyperion_abi::sys::write(self.0, buf).map_err(map_sys_err)
     }
 
-    pub fn write_vectored(&self, _bufs: &[IoSlice<'_>]) -> io::Result<usize> {
-        Err(io::const_io_error!(io::ErrorKind::Unsupported, "write_vectored unsupported"))
+    pub fn write_vectored(&self, bufs: &[IoSlice<'_>]) -> io::Result<usize> {
+        hyperion_abi::sys::writev(self.0, as_bufs(bufs)).map_err(map_sys_err)
     }
 
     pub fn is_write_vectored(&self) -> bool {
-        false
+        true
+    }
+
+    pub fn write_at(&self, buf: &[u8], offset: u64) -> io::Result<usize> {
+        hyperion_abi::sys::pwrite(self.0, buf, Self::offset(offset)?).map_err(map_sys_err)
+    }
+
+    fn offset(offset: u64) -> io::Result<usize> {
+        usize::try_from(offset).map_err(|_| {
+            io::const_io_error!(io::ErrorKind::InvalidInput, "the file offset is too large")
+        })
     }
 
     pub fn flush(&self) -> io::Result<()> {
@@ -277,23 +343,35 @@ pub fn seek(&self, pos: SeekFrom) -> io::Result<u64> {
             SeekFrom::End(offs) => (offs as _, hyperion_abi::sys::fs::Seek::END),
             SeekFrom::Current(offs) => (offs as _, hyperion_abi::sys::fs::Seek::CUR),
         };
-        hyperion_abi::sys::seek(self.0, offs, origin.0).map_err(map_sys_err)?;
-        let mut meta = hyperion_abi::sys::fs::Metadata::zeroed();
-        hyperion_abi::sys::metadata(self.0, &mut meta).map_err(map_sys_err)?;
-        Ok(meta.position as _)
+        // the kernel returns the new position
+        let pos = hyperion_abi::sys::seek(self.0, offs, origin.0).map_err(map_sys_err)?;
+        Ok(pos as _)
     }
 
     pub fn duplicate(&self) -> io::Result<File> {
-        // hyperion_abi::sys::dup(self.0, )
-        Err(io::const_io_error!(io::ErrorKind::Unsupported, "duplicate unsupported"))
+        hyperion_abi::sys::dup(self.0).map(File).map_err(map_sys_err)
     }
 
-    pub fn set_permissions(&self, _perm: FilePermissions) -> io::Result<()> {
-        Err(io::const_io_error!(io::ErrorKind::Unsupported, "set_permissions unsupported"))
+    /// Files, pipes and sockets all use the same syscall.
+    pub fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        hyperion_abi::sys::set_nonblocking(self.0, nonblocking).map_err(map_sys_err)
     }
 
-    pub fn set_times(&self, _times: FileTimes) -> io::Result<()> {
-        Err(io::const_io_error!(io::ErrorKind::Unsupported, "set_times unsupported"))
+    pub fn set_permissions(&self, perm: FilePermissions) -> io::Result<()> {
+        hyperion_abi::sys::set_mode(self.0, perm.mode).map_err(map_sys_err)
+    }
+
+    pub fn set_times(&self, times: FileTimes) -> io::Result<()> {
+        let to_nanos = |time: Option<SystemTime>| match time {
+            Some(time) => time.as_nanos().map(Some).ok_or(io::const_io_error!(
+                io::ErrorKind::InvalidInput,
+                "timestamp is too large to set as a file time"
+            )),
+            None => Ok(None),
+        };
+        let accessed = to_nanos(times.accessed)?;
+        let modified = to_nanos(times.modified)?;
+        hyperion_abi::sys::set_times(self.0, accessed, modified).map_err(map_sys_err)
     }
 }
 
@@ -309,6 +387,14 @@ fn as_inner(&self) -> &FileDesc {
     }
 }
 
+impl IntoInner<FileDesc> for File {
+    fn into_inner(self) -> FileDesc {
+        let fd = self.0;
+        mem::forget(self);
+        fd
+    }
+}
+
 impl DirBuilder {
     pub fn new() -> DirBuilder {
         DirBuilder {}
@@ -331,58 +417,188 @@ fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
     }
 }
 
-pub fn readdir(_p: &Path) -> io::Result<ReadDir> {
-    unsupported()
-}
+pub fn readdir(p: &Path) -> io::Result<ReadDir> {
+    let mut opts = OpenOptions::new();
+    opts.read(true);
+    opts.is_dir(true);
+    let dir = File::open(p, &opts)?;
 
-pub fn unlink(_p: &Path) -> io::Result<()> {
-    unsupported()
+    let inner = Arc::new(InnerReadDir { dir, root: p.to_path_buf() });
+    Ok(ReadDir { inner })
 }
 
-pub fn rename(_old: &Path, _new: &Path) -> io::Result<()> {
-    unsupported()
+pub fn unlink(p: &Path) -> io::Result<()> {
+    run_path_with_str(p, |p| hyperion_abi::sys::unlink(p).map_err(map_sys_err))
 }
 
-pub fn set_perm(_p: &Path, perm: FilePermissions) -> io::Result<()> {
-    match perm.0 {}
+pub fn rename(old: &Path, new: &Path) -> io::Result<()> {
+    run_path_with_str(old, |old| {
+        run_path_with_str(new, |new| rename_path(old, new).map_err(map_sys_err))
+    })
 }
 
-pub fn rmdir(_p: &Path) -> io::Result<()> {
-    unsupported()
+pub fn set_perm(p: &Path, perm: FilePermissions) -> io::Result<()> {
+    // by path, opening the file would need read access to it
+    run_path_with_str(p, |p| hyperion_abi::sys::set_mode_path(p, perm.mode).map_err(map_sys_err))
 }
 
-pub fn remove_dir_all(_path: &Path) -> io::Result<()> {
-    unsupported()
+pub fn rmdir(p: &Path) -> io::Result<()> {
+    run_path_with_str(p, |p| hyperion_abi::sys::rmdir(p).map_err(map_sys_err))
 }
 
-pub fn try_exists(_path: &Path) -> io::Result<bool> {
-    unsupported()
+pub fn readlink(p: &Path) -> io::Result<PathBuf> {
+    run_path_with_str(p, |p| read_path(|buf| hyperion_abi::sys::readlink(p, buf)))
 }
 
-pub fn readlink(_p: &Path) -> io::Result<PathBuf> {
-    unsupported()
+pub fn symlink(original: &Path, link: &Path) -> io::Result<()> {
+    // the link target is stored as is, so relative links stay relative
+    let Some(original) = original.to_str() else {
+        return Err(io::const_io_error!(
+            io::ErrorKind::InvalidFilename,
+            "the path should be UTF-8"
+        ));
+    };
+    run_path_with_str(link, |link| hyperion_abi::sys::symlink(original, link).map_err(map_sys_err))
 }
 
-pub fn symlink(_original: &Path, _link: &Path) -> io::Result<()> {
-    unsupported()
+pub fn link(src: &Path, dst: &Path) -> io::Result<()> {
+    run_path_with_str(src, |src| {
+        run_path_with_str(dst, |dst| hyperion_abi::sys::link(src, dst).map_err(map_sys_err))
+    })
 }
 
-pub fn link(_src: &Path, _dst: &Path) -> io::Result<()> {
-    unsupported()
+pub fn stat(p: &Path) -> io::Result<FileAttr> {
+    stat_with(p, true)
 }
 
-pub fn stat(_p: &Path) -> io::Result<FileAttr> {
-    unsupported()
+pub fn lstat(p: &Path) -> io::Result<FileAttr> {
+    stat_with(p, false)
 }
 
-pub fn lstat(_p: &Path) -> io::Result<FileAttr> {
-    unsupported()
+/// By path, opening the file would need read access to it and could have
+/// side effects on FIFOs and devices.
+fn stat_with(p: &Path, follow: bool) -> io::Result<FileAttr> {
+    let mut meta = Metadata::zeroed();
+    run_path_with_str(p, |p| {
+        hyperion_abi::sys::metadata_path(p, follow, &mut meta).map_err(map_sys_err)
+    })?;
+    Ok(FileAttr(meta))
 }
 
+/// Maximum number of symlinks `canonicalize` follows before giving up.
+const MAX_SYMLINKS: usize = 40;
+
 pub fn canonicalize(p: &Path) -> io::Result<PathBuf> {
-    if p.has_root() { Ok(p.into()) } else { Ok(Path::new("/").join(p)) }
+    let mut links = 0;
+    let mut resolved = PathBuf::from("/");
+
+    // the remaining components in reverse order, so the next one can be popped off
+    let mut rest = Vec::new();
+    push_components(&mut rest, p);
+    if !p.has_root() {
+        push_components(&mut rest, &super::os::getcwd()?);
+    }
+
+    while let Some(name) = rest.pop() {
+        let Some(name) = name else {
+            // everything before the `..` is resolved already, so this goes to
+            // the parent of the link target and not of the link
+            if !stat(&resolved)?.file_type().is_dir() {
+                return Err(io::const_io_error!(io::ErrorKind::NotADirectory, "not a directory"));
+            }
+            // `/..` is `/`
+            resolved.pop();
+            continue;
+        };
+        resolved.push(name);
+
+        // this also makes sure that every component exists
+        if !lstat(&resolved)?.file_type().is_symlink() {
+            continue;
+        }
+
+        links += 1;
+        if links > MAX_SYMLINKS {
+            return Err(io::const_io_error!(
+                io::ErrorKind::FilesystemLoop,
+                "too many levels of symbolic links"
+            ));
+        }
+
+        // continue from the link target, relative targets start in the
+        // directory of the link
+        let target = readlink(&resolved)?;
+        resolved.pop();
+        if target.has_root() {
+            resolved = PathBuf::from("/");
+        }
+        push_components(&mut rest, &target);
+    }
+
+    Ok(resolved)
+}
+
+/// Pushes the components of `path` in reverse order, `None` stands for `..`.
+fn push_components(stack: &mut Vec<Option<OsString>>, path: &Path) {
+    stack.extend(path.components().rev().filter_map(|component| match component {
+        Component::Normal(name) => Some(Some(name.to_os_string())),
+        Component::ParentDir => Some(None),
+        Component::Prefix(_) | Component::RootDir | Component::CurDir => None,
+    }));
+}
+
+/// Joins relative paths onto the working directory. `.` and `..` are left to
+/// the kernel, which applies `..` after following symlinks, like
+/// `canonicalize`.
+fn absolute_path(p: &Path) -> io::Result<Cow<'_, Path>> {
+    if p.has_root() { Ok(Cow::Borrowed(p)) } else { Ok(Cow::Owned(super::os::getcwd()?.join(p))) }
+}
+
+pub fn copy(from: &Path, to: &Path) -> io::Result<u64> {
+    let mut reader = crate::fs::File::open(from)?;
+    if !reader.metadata()?.is_file() {
+        return Err(crate::sys_common::fs::NOT_FILE_ERROR);
+    }
+
+    let perm = reader.metadata()?.permissions();
+    let mut writer = crate::fs::File::create(to)?;
+    let written = io::copy(&mut reader, &mut writer)?;
+    writer.set_permissions(perm)?;
+    Ok(written)
 }
 
-pub fn copy(_from: &Path, _to: &Path) -> io::Result<u64> {
-    unsupported()
+pub(crate) fn run_path_with_str<T>(
+    p: &Path,
+    f: impl FnOnce(&str) -> io::Result<T>,
+) -> io::Result<T> {
+    let path = absolute_path(p)?;
+    let Some(path) = path.to_str() else {
+        return Err(io::const_io_error!(
+            io::ErrorKind::InvalidFilename,
+            "the path should be UTF-8"
+        ));
+    };
+
+    f(path)
+}
+
+/// Reads a path with a syscall that truncates it to fit into the buffer and
+/// returns its full length.
+pub(crate) fn read_path(mut f: impl FnMut(&mut [u8]) -> SysResult<usize>) -> io::Result<PathBuf> {
+    let mut buf = vec![0; 256];
+
+    loop {
+        let len = f(&mut buf).map_err(map_sys_err)?;
+
+        if len < buf.len() {
+            buf.truncate(len);
+            let path = String::from_utf8(buf).map_err(|_| {
+                io::const_io_error!(io::ErrorKind::InvalidData, "the path should be UTF-8")
+            })?;
+            return Ok(PathBuf::from(path));
+        }
+
+        // the path might have been truncated, try again with a bigger buffer
+        buf.resize(buf.len() * 2, 0);
+    }
 }
@@ -0,0 +1,51 @@
+use hyperion_abi::sys::err::Error;
+use hyperion_abi::sys::{
+    futex_wait as sys_futex_wait, futex_wait_until, futex_wake as sys_futex_wake,
+};
+
+use crate::sync::atomic::AtomicUsize;
+use crate::sys::time::Instant;
+use crate::time::Duration;
+
+//
+
+/// Waits for a `futex_wake` operation to wake us.
+///
+/// Returns directly if the futex doesn't hold the expected value.
+///
+/// Returns false on timeout, and true in all other cases.
+pub fn futex_wait(futex: &AtomicUsize, expected: usize, timeout: Option<Duration>) -> bool {
+    // Calculate the deadline, if there is one. The kernel takes an absolute
+    // deadline, so retrying after an interruption doesn't extend the wait.
+    // An overflowing deadline is treated as no timeout at all.
+    let Some(deadline) = timeout.and_then(|d| Instant::now().checked_add_duration(&d)) else {
+        sys_futex_wait(futex, expected);
+        return true;
+    };
+
+    loop {
+        match futex_wait_until(futex, expected, deadline.as_nanos()) {
+            Err(Error::TIMED_OUT) => return false,
+            Err(Error::INTERRUPTED) => continue,
+            _ => return true,
+        }
+    }
+}
+
+/// Wakes up one thread that's blocked on `futex_wait` on this futex.
+///
+/// Returns true if this actually woke up such a thread,
+/// or false if no thread was waiting on this futex.
+///
+/// The kernel doesn't tell us whether it woke up any threads or not, so this
+/// always returns false. That still results in correct behaviour, callers
+/// just can't skip any fallback wakeups.
+pub fn futex_wake(futex: &AtomicUsize) -> bool {
+    sys_futex_wake(futex, 1);
+    false
+}
+
+/// Wakes up all threads that are waiting on `futex_wait` on this futex.
+pub fn futex_wake_all(futex: &AtomicUsize) {
+    sys_futex_wake(futex, usize::MAX);
+}
@@ -1,8 +1,13 @@
+use hyperion_abi::sys::fs::FileDesc;
+use hyperion_abi::sys::isatty;
+
 use crate::mem;
+use crate::os::hyperion::io::{AsFd, AsRawFd};
 
 //
 
 #[derive(Copy, Clone)]
+#[repr(transparent)]
 pub struct IoSlice<'a>(&'a [u8]);
 
 impl<'a> IoSlice<'a> {
@@ -22,6 +27,7 @@ pub fn as_slice(&self) -> &[u8] {
     }
 }
 
+#[repr(transparent)]
 pub struct IoSliceMut<'a>(&'a mut [u8]);
 
 impl<'a> IoSliceMut<'a> {
@@ -48,6 +54,24 @@ pub fn as_mut_slice(&mut self) -> &mut [u8] {
     }
 }
 
-pub fn is_terminal<T>(_: &T) -> bool {
-    false
+pub fn is_terminal(fd: &impl AsFd) -> bool {
+    let fd = FileDesc(fd.as_fd().as_raw_fd());
+    // anything that can't be asked isn't a terminal either
+    isatty(fd).unwrap_or(false)
+}
+
+/// `IoSlice` has the same layout as `&[u8]`, so a whole list of them can be
+/// handed to the kernel at once.
+pub(crate) fn as_bufs<'s, 'a>(bufs: &'s [crate::io::IoSlice<'a>]) -> &'s [&'a [u8]] {
+    // SAFETY: both `IoSlice` types are `repr(transparent)` wrappers around `&[u8]`
+    unsafe { &*(bufs as *const [crate::io::IoSlice<'a>] as *const [&'a [u8]]) }
+}
+
+/// `IoSliceMut` has the same layout as `&mut [u8]`, so a whole list of them
+/// can be handed to the kernel at once.
+pub(crate) fn as_bufs_mut<'s, 'a>(
+    bufs: &'s mut [crate::io::IoSliceMut<'a>],
+) -> &'s mut [&'a mut [u8]] {
+    // SAFETY: both `IoSliceMut` types are `repr(transparent)` wrappers around `&mut [u8]`
+    unsafe { &mut *(bufs as *mut [crate::io::IoSliceMut<'a>] as *mut [&'a mut [u8]]) }
 }
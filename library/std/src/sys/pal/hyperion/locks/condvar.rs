@@ -1,10 +1,8 @@
-use hyperion_abi::sys::{futex_wait, futex_wake};
-
+use crate::sync::atomic::{AtomicUsize, Ordering};
+use crate::sys::futex::{futex_wait, futex_wake, futex_wake_all};
 use crate::sys::locks::Mutex;
 use crate::time::Duration;
 
-use crate::sync::atomic::{AtomicUsize, Ordering};
-
 //
 
 pub struct Condvar {
@@ -21,13 +19,13 @@ pub const fn new() -> Condvar {
     #[inline]
     pub fn notify_one(&self) {
         self.futex.fetch_add(1, Ordering::Relaxed);
-        futex_wake(&self.futex, 1)
+        futex_wake(&self.futex);
     }
 
     #[inline]
     pub fn notify_all(&self) {
         self.futex.fetch_add(1, Ordering::Relaxed);
-        futex_wake(&self.futex, usize::MAX)
+        futex_wake_all(&self.futex);
     }
 
     pub unsafe fn wait(&self, mutex: &Mutex) {
@@ -47,11 +45,7 @@ unsafe fn wait_optional_timeout(&self, mutex: &Mutex, timeout: Option<Duration>)
 
         // Wait, but only if there hasn't been any
         // notification since we unlocked the mutex.
-        // let r = ..
-        futex_wait(&self.futex, futex_value); // FIXME: timeout
-
-        _ = timeout;
-        let r = false;
+        let r = futex_wait(&self.futex, futex_value, timeout);
 
         // Lock the mutex again.
         mutex.lock();
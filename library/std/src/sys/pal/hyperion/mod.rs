@@ -7,8 +7,14 @@
 
 pub mod alloc;
 pub mod args;
+pub mod backtrace;
+#[cfg(not(test))]
+mod eh_unwinding;
+#[cfg(not(test))]
+mod elf;
 pub mod env;
 pub mod fs;
+pub mod futex;
 pub mod io;
 pub mod locks;
 pub mod net;
@@ -16,6 +22,10 @@
 pub mod os;
 pub mod pipe;
 pub mod process;
+pub mod rand;
+#[cfg(not(test))]
+mod reloc;
+mod stack_overflow;
 pub mod stdio;
 pub mod thread;
 #[cfg(target_thread_local)]
@@ -30,23 +40,62 @@
 
 //
 
+/// The kernel enters the binary here, passing the argument and environment
+/// blocks in the first two argument registers of the platform's C ABI.
+#[cfg(not(test))]
 #[no_mangle]
-extern "sysv64" fn _start(hyperion_cli_args_ptr: usize, _a2: usize) -> ! {
+extern "C" fn _start(hyperion_cli_args_ptr: usize, hyperion_env_ptr: usize) -> ! {
+    // the binary is static-PIE, nothing may read a pointer from its data
+    // before this
+    #[cfg(not(target_env = "hosted"))]
+    unsafe {
+        reloc::relocate()
+    };
+
     // rustc generates the real `main` function, that fn
     // simply calls `lang_start` with the correct args
     extern "C" {
         fn main(argc: isize, argv: *const *const u8) -> i32;
     }
 
+    // let panics unwind through this binary's frames, and backtraces walk
+    // them even with `panic=abort`
+    {
+        eh_unwinding::EH_FRAME_FINDER.init();
+        unwind::set_custom_eh_frame_finder(&eh_unwinding::EH_FRAME_FINDER).ok();
+    }
+
     // init cli args from stack, move them to the heap
     unsafe { args::init_args(hyperion_cli_args_ptr) };
 
+    // init env vars from stack, move them to the heap
+    unsafe { os::init_env(hyperion_env_ptr) };
+
     // call `lang_start`
     let exit_code = unsafe { main(0, ptr::null()) };
 
+    // run the main thread's TLS destructors
+    #[cfg(target_thread_local)]
+    unsafe {
+        thread_local_dtor::run_dtors()
+    };
+
     ExitCode::from_raw(exit_code as _).exit_process();
 }
 
+/// The hosted shim's entry point calls this instead of `_start` relocating the
+/// binary, because it copies the TLS image, pointers included, before `_start`.
+///
+/// # Safety
+///
+/// Has to be called exactly once, before anything reads a pointer from the
+/// binary's data.
+#[cfg(all(not(test), target_env = "hosted"))]
+#[no_mangle]
+unsafe extern "C" fn __hyperion_linux_relocate() {
+    unsafe { reloc::relocate() }
+}
+
 #[cfg(not(test))]
 #[no_mangle]
 pub extern "C" fn __rust_abort() {
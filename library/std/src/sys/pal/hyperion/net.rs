@@ -1,307 +1,476 @@
+use hyperion_abi::sys::fs::FileDesc;
+use hyperion_abi::sys::net::{
+    Protocol, RecvFlags, Shutdown as SysShutdown, SockOpt, SocketDomain, SocketType,
+};
+use hyperion_abi::sys::{
+    accept, bind, connect, get_sock_opt, local_addr, peer_addr, recv, recv_from, send, send_to,
+    set_sock_opt, socket,
+};
+
 use crate::fmt;
 use crate::io::{self, BorrowedCursor, IoSlice, IoSliceMut};
 use crate::net::{Ipv4Addr, Ipv6Addr, Shutdown, SocketAddr};
+use crate::os::hyperion::{map_sys_err, to_sys_err};
+use crate::str;
+use crate::sys::fs::File;
 use crate::sys::unsupported;
+use crate::sys_common::AsInner;
 use crate::time::Duration;
 
-pub struct TcpStream(!);
+//
+
+/// Socket addresses are passed to and from the kernel as strings,
+/// this is enough for any `SocketAddr`.
+const ADDR_BUF_LEN: usize = 64;
+
+//
+
+pub struct TcpStream {
+    inner: Socket,
+}
 
 impl TcpStream {
-    pub fn connect(_: io::Result<&SocketAddr>) -> io::Result<TcpStream> {
-        unsupported()
+    pub fn connect(addr: io::Result<&SocketAddr>) -> io::Result<TcpStream> {
+        let addr = addr?;
+        let inner = Socket::new(addr, SocketType::STREAM, Protocol::TCP)?;
+        connect(inner.fd(), &addr.to_string()).map_err(map_sys_err)?;
+        Ok(TcpStream { inner })
     }
 
-    pub fn connect_timeout(_: &SocketAddr, _: Duration) -> io::Result<TcpStream> {
-        unsupported()
+    pub fn connect_timeout(addr: &SocketAddr, timeout: Duration) -> io::Result<TcpStream> {
+        let inner = Socket::new(addr, SocketType::STREAM, Protocol::TCP)?;
+        inner.set_timeout(Some(timeout), SockOpt::CONNECT_TIMEOUT)?;
+        let connected = connect(inner.fd(), &addr.to_string()).map_err(map_sys_err);
+        // the connect timeout stays on the socket, reset it so it doesn't
+        // apply to anything later
+        let reset = inner.set_timeout(None, SockOpt::CONNECT_TIMEOUT);
+        connected?;
+        reset?;
+        Ok(TcpStream { inner })
     }
 
-    pub fn set_read_timeout(&self, _: Option<Duration>) -> io::Result<()> {
-        self.0
+    pub fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        self.inner.set_timeout(timeout, SockOpt::READ_TIMEOUT)
     }
 
-    pub fn set_write_timeout(&self, _: Option<Duration>) -> io::Result<()> {
-        self.0
+    pub fn set_write_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        self.inner.set_timeout(timeout, SockOpt::WRITE_TIMEOUT)
     }
 
     pub fn read_timeout(&self) -> io::Result<Option<Duration>> {
-        self.0
+        self.inner.timeout(SockOpt::READ_TIMEOUT)
     }
 
     pub fn write_timeout(&self) -> io::Result<Option<Duration>> {
-        self.0
+        self.inner.timeout(SockOpt::WRITE_TIMEOUT)
     }
 
-    pub fn peek(&self, _: &mut [u8]) -> io::Result<usize> {
-        self.0
+    pub fn peek(&self, buf: &mut [u8]) -> io::Result<usize> {
+        self.inner.recv_with_flags(buf, RecvFlags::PEEK)
     }
 
-    pub fn read(&self, _: &mut [u8]) -> io::Result<usize> {
-        self.0
+    pub fn read(&self, buf: &mut [u8]) -> io::Result<usize> {
+        self.inner.recv_with_flags(buf, RecvFlags::empty())
     }
 
-    pub fn read_buf(&self, _buf: BorrowedCursor<'_>) -> io::Result<()> {
-        self.0
+    pub fn read_buf(&self, buf: BorrowedCursor<'_>) -> io::Result<()> {
+        crate::io::default_read_buf(|buf| self.read(buf), buf)
     }
 
-    pub fn read_vectored(&self, _: &mut [IoSliceMut<'_>]) -> io::Result<usize> {
-        self.0
+    pub fn read_vectored(&self, bufs: &mut [IoSliceMut<'_>]) -> io::Result<usize> {
+        crate::io::default_read_vectored(|buf| self.read(buf), bufs)
     }
 
     pub fn is_read_vectored(&self) -> bool {
-        self.0
+        false
     }
 
-    pub fn write(&self, _: &[u8]) -> io::Result<usize> {
-        self.0
+    pub fn write(&self, buf: &[u8]) -> io::Result<usize> {
+        send(self.inner.fd(), buf, 0).map_err(map_sys_err)
     }
 
-    pub fn write_vectored(&self, _: &[IoSlice<'_>]) -> io::Result<usize> {
-        self.0
+    pub fn write_vectored(&self, bufs: &[IoSlice<'_>]) -> io::Result<usize> {
+        crate::io::default_write_vectored(|buf| self.write(buf), bufs)
     }
 
     pub fn is_write_vectored(&self) -> bool {
-        self.0
+        false
     }
 
     pub fn peer_addr(&self) -> io::Result<SocketAddr> {
-        self.0
+        self.inner.peer_addr()
     }
 
     pub fn socket_addr(&self) -> io::Result<SocketAddr> {
-        self.0
+        self.inner.socket_addr()
     }
 
-    pub fn shutdown(&self, _: Shutdown) -> io::Result<()> {
-        self.0
+    pub fn shutdown(&self, how: Shutdown) -> io::Result<()> {
+        self.inner.shutdown(how)
     }
 
     pub fn duplicate(&self) -> io::Result<TcpStream> {
-        self.0
+        Ok(TcpStream { inner: self.inner.duplicate()? })
     }
 
     pub fn set_linger(&self, _: Option<Duration>) -> io::Result<()> {
-        self.0
+        unsupported()
     }
 
     pub fn linger(&self) -> io::Result<Option<Duration>> {
-        self.0
+        unsupported()
     }
 
-    pub fn set_nodelay(&self, _: bool) -> io::Result<()> {
-        self.0
+    pub fn set_nodelay(&self, nodelay: bool) -> io::Result<()> {
+        self.inner.set_opt(SockOpt::NODELAY, nodelay as usize)
     }
 
     pub fn nodelay(&self) -> io::Result<bool> {
-        self.0
+        Ok(self.inner.opt(SockOpt::NODELAY)? != 0)
     }
 
-    pub fn set_ttl(&self, _: u32) -> io::Result<()> {
-        self.0
+    pub fn set_ttl(&self, ttl: u32) -> io::Result<()> {
+        self.inner.set_opt(SockOpt::TTL, ttl as usize)
     }
 
     pub fn ttl(&self) -> io::Result<u32> {
-        self.0
+        Ok(self.inner.opt(SockOpt::TTL)? as u32)
     }
 
     pub fn take_error(&self) -> io::Result<Option<io::Error>> {
-        self.0
+        self.inner.take_error()
     }
 
-    pub fn set_nonblocking(&self, _: bool) -> io::Result<()> {
-        self.0
+    pub fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        self.inner.0.set_nonblocking(nonblocking)
     }
 }
 
 impl fmt::Debug for TcpStream {
-    fn fmt(&self, _f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        self.0
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut res = f.debug_struct("TcpStream");
+
+        if let Ok(addr) = self.socket_addr() {
+            res.field("addr", &addr);
+        }
+
+        if let Ok(peer) = self.peer_addr() {
+            res.field("peer", &peer);
+        }
+
+        res.field("fd", &self.inner.fd().0).finish()
     }
 }
 
-pub struct TcpListener(!);
+pub struct TcpListener {
+    inner: Socket,
+}
 
 impl TcpListener {
-    pub fn bind(_: io::Result<&SocketAddr>) -> io::Result<TcpListener> {
-        unsupported()
+    pub fn bind(addr: io::Result<&SocketAddr>) -> io::Result<TcpListener> {
+        let addr = addr?;
+        let inner = Socket::new(addr, SocketType::STREAM, Protocol::TCP)?;
+        bind(inner.fd(), &addr.to_string()).map_err(map_sys_err)?;
+        Ok(TcpListener { inner })
     }
 
     pub fn socket_addr(&self) -> io::Result<SocketAddr> {
-        self.0
+        self.inner.socket_addr()
     }
 
     pub fn accept(&self) -> io::Result<(TcpStream, SocketAddr)> {
-        self.0
+        let fd = accept(self.inner.fd()).map_err(map_sys_err)?;
+        let stream = TcpStream { inner: Socket(File::from_inner(fd)) };
+        let addr = stream.peer_addr()?;
+        Ok((stream, addr))
     }
 
     pub fn duplicate(&self) -> io::Result<TcpListener> {
-        self.0
+        Ok(TcpListener { inner: self.inner.duplicate()? })
     }
 
-    pub fn set_ttl(&self, _: u32) -> io::Result<()> {
-        self.0
+    pub fn set_ttl(&self, ttl: u32) -> io::Result<()> {
+        self.inner.set_opt(SockOpt::TTL, ttl as usize)
     }
 
     pub fn ttl(&self) -> io::Result<u32> {
-        self.0
+        Ok(self.inner.opt(SockOpt::TTL)? as u32)
     }
 
-    pub fn set_only_v6(&self, _: bool) -> io::Result<()> {
-        self.0
+    pub fn set_only_v6(&self, only_v6: bool) -> io::Result<()> {
+        self.inner.set_opt(SockOpt::ONLY_V6, only_v6 as usize)
     }
 
     pub fn only_v6(&self) -> io::Result<bool> {
-        self.0
+        Ok(self.inner.opt(SockOpt::ONLY_V6)? != 0)
     }
 
     pub fn take_error(&self) -> io::Result<Option<io::Error>> {
-        self.0
+        self.inner.take_error()
     }
 
-    pub fn set_nonblocking(&self, _: bool) -> io::Result<()> {
-        self.0
+    pub fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        self.inner.0.set_nonblocking(nonblocking)
     }
 }
 
 impl fmt::Debug for TcpListener {
-    fn fmt(&self, _f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        self.0
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut res = f.debug_struct("TcpListener");
+
+        if let Ok(addr) = self.socket_addr() {
+            res.field("addr", &addr);
+        }
+
+        res.field("fd", &self.inner.fd().0).finish()
     }
 }
 
-pub struct UdpSocket(!);
+pub struct UdpSocket {
+    inner: Socket,
+}
 
 impl UdpSocket {
-    pub fn bind(_: io::Result<&SocketAddr>) -> io::Result<UdpSocket> {
-        unsupported()
+    pub fn bind(addr: io::Result<&SocketAddr>) -> io::Result<UdpSocket> {
+        let addr = addr?;
+        let inner = Socket::new(addr, SocketType::DGRAM, Protocol::UDP)?;
+        bind(inner.fd(), &addr.to_string()).map_err(map_sys_err)?;
+        Ok(UdpSocket { inner })
     }
 
     pub fn peer_addr(&self) -> io::Result<SocketAddr> {
-        self.0
+        self.inner.peer_addr()
     }
 
     pub fn socket_addr(&self) -> io::Result<SocketAddr> {
-        self.0
+        self.inner.socket_addr()
     }
 
-    pub fn recv_from(&self, _: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
-        self.0
+    pub fn recv_from(&self, buf: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
+        self.inner.recv_from_with_flags(buf, RecvFlags::empty())
     }
 
-    pub fn peek_from(&self, _: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
-        self.0
+    pub fn peek_from(&self, buf: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
+        self.inner.recv_from_with_flags(buf, RecvFlags::PEEK)
     }
 
-    pub fn send_to(&self, _: &[u8], _: &SocketAddr) -> io::Result<usize> {
-        self.0
+    pub fn send_to(&self, buf: &[u8], dst: &SocketAddr) -> io::Result<usize> {
+        send_to(self.inner.fd(), buf, 0, &dst.to_string()).map_err(map_sys_err)
     }
 
     pub fn duplicate(&self) -> io::Result<UdpSocket> {
-        self.0
+        Ok(UdpSocket { inner: self.inner.duplicate()? })
     }
 
-    pub fn set_read_timeout(&self, _: Option<Duration>) -> io::Result<()> {
-        self.0
+    pub fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        self.inner.set_timeout(timeout, SockOpt::READ_TIMEOUT)
     }
 
-    pub fn set_write_timeout(&self, _: Option<Duration>) -> io::Result<()> {
-        self.0
+    pub fn set_write_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        self.inner.set_timeout(timeout, SockOpt::WRITE_TIMEOUT)
     }
 
     pub fn read_timeout(&self) -> io::Result<Option<Duration>> {
-        self.0
+        self.inner.timeout(SockOpt::READ_TIMEOUT)
     }
 
     pub fn write_timeout(&self) -> io::Result<Option<Duration>> {
-        self.0
+        self.inner.timeout(SockOpt::WRITE_TIMEOUT)
     }
 
-    pub fn set_broadcast(&self, _: bool) -> io::Result<()> {
-        self.0
+    pub fn set_broadcast(&self, broadcast: bool) -> io::Result<()> {
+        self.inner.set_opt(SockOpt::BROADCAST, broadcast as usize)
     }
 
     pub fn broadcast(&self) -> io::Result<bool> {
-        self.0
+        Ok(self.inner.opt(SockOpt::BROADCAST)? != 0)
     }
 
     pub fn set_multicast_loop_v4(&self, _: bool) -> io::Result<()> {
-        self.0
+        unsupported()
     }
 
     pub fn multicast_loop_v4(&self) -> io::Result<bool> {
-        self.0
+        unsupported()
     }
 
     pub fn set_multicast_ttl_v4(&self, _: u32) -> io::Result<()> {
-        self.0
+        unsupported()
     }
 
     pub fn multicast_ttl_v4(&self) -> io::Result<u32> {
-        self.0
+        unsupported()
     }
 
     pub fn set_multicast_loop_v6(&self, _: bool) -> io::Result<()> {
-        self.0
+        unsupported()
     }
 
     pub fn multicast_loop_v6(&self) -> io::Result<bool> {
-        self.0
+        unsupported()
     }
 
     pub fn join_multicast_v4(&self, _: &Ipv4Addr, _: &Ipv4Addr) -> io::Result<()> {
-        self.0
+        unsupported()
     }
 
     pub fn join_multicast_v6(&self, _: &Ipv6Addr, _: u32) -> io::Result<()> {
-        self.0
+        unsupported()
     }
 
     pub fn leave_multicast_v4(&self, _: &Ipv4Addr, _: &Ipv4Addr) -> io::Result<()> {
-        self.0
+        unsupported()
     }
 
     pub fn leave_multicast_v6(&self, _: &Ipv6Addr, _: u32) -> io::Result<()> {
-        self.0
+        unsupported()
     }
 
-    pub fn set_ttl(&self, _: u32) -> io::Result<()> {
-        self.0
+    pub fn set_ttl(&self, ttl: u32) -> io::Result<()> {
+        self.inner.set_opt(SockOpt::TTL, ttl as usize)
     }
 
     pub fn ttl(&self) -> io::Result<u32> {
-        self.0
+        Ok(self.inner.opt(SockOpt::TTL)? as u32)
     }
 
     pub fn take_error(&self) -> io::Result<Option<io::Error>> {
-        self.0
+        self.inner.take_error()
     }
 
-    pub fn set_nonblocking(&self, _: bool) -> io::Result<()> {
-        self.0
+    pub fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        self.inner.0.set_nonblocking(nonblocking)
     }
 
-    pub fn recv(&self, _: &mut [u8]) -> io::Result<usize> {
-        self.0
+    pub fn recv(&self, buf: &mut [u8]) -> io::Result<usize> {
+        self.inner.recv_with_flags(buf, RecvFlags::empty())
     }
 
-    pub fn peek(&self, _: &mut [u8]) -> io::Result<usize> {
-        self.0
+    pub fn peek(&self, buf: &mut [u8]) -> io::Result<usize> {
+        self.inner.recv_with_flags(buf, RecvFlags::PEEK)
     }
 
-    pub fn send(&self, _: &[u8]) -> io::Result<usize> {
-        self.0
+    pub fn send(&self, buf: &[u8]) -> io::Result<usize> {
+        send(self.inner.fd(), buf, 0).map_err(map_sys_err)
     }
 
-    pub fn connect(&self, _: io::Result<&SocketAddr>) -> io::Result<()> {
-        self.0
+    pub fn connect(&self, addr: io::Result<&SocketAddr>) -> io::Result<()> {
+        connect(self.inner.fd(), &addr?.to_string()).map_err(map_sys_err)
     }
 }
 
 impl fmt::Debug for UdpSocket {
-    fn fmt(&self, _f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        self.0
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut res = f.debug_struct("UdpSocket");
+
+        if let Ok(addr) = self.socket_addr() {
+            res.field("addr", &addr);
+        }
+
+        res.field("fd", &self.inner.fd().0).finish()
     }
 }
 
+//
+
+/// An INET socket, closed on drop.
+struct Socket(File);
+
+impl Socket {
+    fn new(addr: &SocketAddr, ty: SocketType, protocol: Protocol) -> io::Result<Socket> {
+        let domain = match addr {
+            SocketAddr::V4(_) => SocketDomain::INET,
+            SocketAddr::V6(_) => SocketDomain::INET6,
+        };
+        let fd = socket(domain, ty, protocol).map_err(map_sys_err)?;
+        Ok(Socket(File::from_inner(fd)))
+    }
+
+    fn fd(&self) -> FileDesc {
+        *self.0.as_inner()
+    }
+
+    fn duplicate(&self) -> io::Result<Socket> {
+        self.0.duplicate().map(Socket)
+    }
+
+    fn recv_with_flags(&self, buf: &mut [u8], flags: RecvFlags) -> io::Result<usize> {
+        recv(self.fd(), buf, flags.bits()).map_err(map_sys_err)
+    }
+
+    fn recv_from_with_flags(
+        &self,
+        buf: &mut [u8],
+        flags: RecvFlags,
+    ) -> io::Result<(usize, SocketAddr)> {
+        let mut addr = [0; ADDR_BUF_LEN];
+        let (n, addr_len) =
+            recv_from(self.fd(), buf, flags.bits(), &mut addr).map_err(map_sys_err)?;
+        Ok((n, parse_addr(&addr[..addr_len])?))
+    }
+
+    fn peer_addr(&self) -> io::Result<SocketAddr> {
+        let mut addr = [0; ADDR_BUF_LEN];
+        let len = peer_addr(self.fd(), &mut addr).map_err(map_sys_err)?;
+        parse_addr(&addr[..len])
+    }
+
+    fn socket_addr(&self) -> io::Result<SocketAddr> {
+        let mut addr = [0; ADDR_BUF_LEN];
+        let len = local_addr(self.fd(), &mut addr).map_err(map_sys_err)?;
+        parse_addr(&addr[..len])
+    }
+
+    fn shutdown(&self, how: Shutdown) -> io::Result<()> {
+        let how = match how {
+            Shutdown::Read => SysShutdown::READ,
+            Shutdown::Write => SysShutdown::WRITE,
+            Shutdown::Both => SysShutdown::BOTH,
+        };
+        hyperion_abi::sys::shutdown(self.fd(), how).map_err(map_sys_err)
+    }
+
+    fn opt(&self, opt: SockOpt) -> io::Result<usize> {
+        get_sock_opt(self.fd(), opt).map_err(map_sys_err)
+    }
+
+    fn set_opt(&self, opt: SockOpt, val: usize) -> io::Result<()> {
+        set_sock_opt(self.fd(), opt, val).map_err(map_sys_err)
+    }
+
+    /// timeouts are stored in nanoseconds, `0` means no timeout
+    fn set_timeout(&self, dur: Option<Duration>, opt: SockOpt) -> io::Result<()> {
+        let nanos = match dur {
+            Some(dur) if dur.is_zero() => {
+                return Err(io::const_io_error!(
+                    io::ErrorKind::InvalidInput,
+                    "cannot set a 0 duration timeout",
+                ));
+            }
+            Some(dur) => dur.as_nanos().try_into().unwrap_or(usize::MAX),
+            None => 0,
+        };
+        self.set_opt(opt, nanos)
+    }
+
+    fn timeout(&self, opt: SockOpt) -> io::Result<Option<Duration>> {
+        let nanos = self.opt(opt)?;
+        Ok((nanos != 0).then(|| Duration::from_nanos(nanos as u64)))
+    }
+
+    fn take_error(&self) -> io::Result<Option<io::Error>> {
+        let err = self.opt(SockOpt::ERROR)?;
+        Ok((err != 0).then(|| map_sys_err(to_sys_err(err as i32))))
+    }
+}
+
+fn parse_addr(addr: &[u8]) -> io::Result<SocketAddr> {
+    str::from_utf8(addr).ok().and_then(|addr| addr.parse().ok()).ok_or(io::const_io_error!(
+        io::ErrorKind::InvalidData,
+        "the kernel returned an invalid socket address"
+    ))
+}
+
 pub struct LookupHost(!);
 
 impl LookupHost {
@@ -1,11 +1,10 @@
-use hyperion_abi::sys::futex_wait;
-
 use crate::cell::Cell;
 use crate::sync as public;
 use crate::sync::{
     atomic::{AtomicUsize, Ordering},
     once::ExclusiveState,
 };
+use crate::sys::futex::{futex_wait, futex_wake_all};
 
 //
 
@@ -31,6 +30,8 @@ struct CompletionGuard<'a> {
 impl<'a> Drop for CompletionGuard<'a> {
     fn drop(&mut self) {
         self.state.store(self.set_state_on_drop_to, Ordering::Release);
+        // wake up the threads waiting for this initialization to finish
+        futex_wake_all(self.state);
     }
 }
 
@@ -71,7 +72,7 @@ pub fn call(&self, ignore_poisoning: bool, f: &mut impl FnMut(&public::OnceState
                     panic!("Once instance has previously been poisoned");
                 }
                 RUNNING => {
-                    futex_wait(&self.state, RUNNING);
+                    futex_wait(&self.state, RUNNING, None);
                     // panic!("one-time initialization may not be performed recursively");
                 }
                 COMPLETE => return,
@@ -1,16 +1,30 @@
-use super::unsupported;
+use super::args::CliArgs;
+use crate::cell::Cell;
+use crate::collections::BTreeMap;
 use crate::error::Error as StdError;
 use crate::ffi::{OsStr, OsString};
 use crate::fmt;
 use crate::io;
 use crate::marker::PhantomData;
-use crate::os::hyperion::to_sys_err;
+use crate::os::hyperion::{map_sys_err, to_sys_err};
 use crate::path::{self, PathBuf};
+use crate::sync::{PoisonError, RwLock};
+use crate::sys::fs::{read_path, run_path_with_str};
+use crate::vec;
 
 //
 
+// Hyperion syscalls return their errors directly instead of through an
+// errno, so this keeps the last error std saw for `io::Error::last_os_error`
+#[thread_local]
+static LAST_ERROR: Cell<i32> = Cell::new(0);
+
 pub fn errno() -> i32 {
-    panic!("errno not supported (it sucks)");
+    LAST_ERROR.get()
+}
+
+pub(crate) fn set_errno(errno: i32) {
+    LAST_ERROR.set(errno);
 }
 
 pub fn error_string(errno: i32) -> String {
@@ -18,11 +32,12 @@ pub fn error_string(errno: i32) -> String {
 }
 
 pub fn getcwd() -> io::Result<PathBuf> {
-    unsupported()
+    read_path(hyperion_abi::sys::get_cwd)
 }
 
-pub fn chdir(_: &path::Path) -> io::Result<()> {
-    unsupported()
+pub fn chdir(p: &path::Path) -> io::Result<()> {
+    // the kernel checks that the directory exists
+    run_path_with_str(p, |p| hyperion_abi::sys::chdir(p).map_err(map_sys_err))
 }
 
 pub struct SplitPaths<'a>(!, PhantomData<&'a ()>);
@@ -63,48 +78,105 @@ fn description(&self) -> &str {
 }
 
 pub fn current_exe() -> io::Result<PathBuf> {
-    unsupported()
+    // the kernel remembers the absolute path the process was spawned from
+    read_path(hyperion_abi::sys::exe_path)
+}
+
+static ENV: RwLock<BTreeMap<OsString, OsString>> = RwLock::new(BTreeMap::new());
+
+pub(crate) unsafe fn init_env(hyperion_env_ptr: usize) {
+    // older kernels don't pass any env vars
+    if hyperion_env_ptr == 0 {
+        return;
+    }
+
+    let vars = CliArgs { hyperion_cli_args_ptr: hyperion_env_ptr };
+    let mut env = ENV.write().unwrap_or_else(PoisonError::into_inner);
+    for var in vars.iter() {
+        if let Some((key, value)) = parse(var) {
+            env.insert(key.into(), value.into());
+        }
+    }
+
+    fn parse(input: &str) -> Option<(&str, &str)> {
+        // Strategy (copied from glibc): Variable name and value are separated
+        // by an ASCII equals sign '='. Since a variable name must not be
+        // empty, allow variable names starting with an equals sign. Skip all
+        // malformed lines.
+        if input.is_empty() {
+            return None;
+        }
+        // the first char may be multibyte, but `=` never is, so the byte
+        // search can't end up in the middle of a char
+        let pos = input.bytes().skip(1).position(|b| b == b'=')? + 1;
+        Some((&input[..pos], &input[pos + 1..]))
+    }
 }
 
-pub struct Env(!);
+pub struct Env {
+    iter: vec::IntoIter<(OsString, OsString)>,
+}
+
+// FIXME(https://github.com/rust-lang/rust/issues/114583): Remove this when <OsStr as Debug>::fmt matches <str as Debug>::fmt.
+pub struct EnvStrDebug<'a> {
+    slice: &'a [(OsString, OsString)],
+}
+
+impl fmt::Debug for EnvStrDebug<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self { slice } = self;
+        f.debug_list()
+            .entries(slice.iter().map(|(a, b)| (a.to_str().unwrap(), b.to_str().unwrap())))
+            .finish()
+    }
+}
 
 impl Env {
-    // FIXME(https://github.com/rust-lang/rust/issues/114583): Remove this when <OsStr as Debug>::fmt matches <str as Debug>::fmt.
     pub fn str_debug(&self) -> impl fmt::Debug + '_ {
-        let Self(inner) = self;
-        match *inner {}
+        let Self { iter } = self;
+        EnvStrDebug { slice: iter.as_slice() }
     }
 }
 
 impl fmt::Debug for Env {
-    fn fmt(&self, _: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let Self(inner) = self;
-        match *inner {}
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self { iter } = self;
+        f.debug_list().entries(iter.as_slice()).finish()
     }
 }
 
+impl !Send for Env {}
+impl !Sync for Env {}
+
 impl Iterator for Env {
     type Item = (OsString, OsString);
     fn next(&mut self) -> Option<(OsString, OsString)> {
-        let Self(inner) = self;
-        match *inner {}
+        self.iter.next()
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
     }
 }
 
 pub fn env() -> Env {
-    panic!("not supported on this platform")
+    let env = ENV.read().unwrap_or_else(PoisonError::into_inner);
+    let result = env.iter().map(|(k, v)| (k.clone(), v.clone())).collect::<Vec<_>>();
+    Env { iter: result.into_iter() }
 }
 
-pub fn getenv(_: &OsStr) -> Option<OsString> {
-    None
+pub fn getenv(k: &OsStr) -> Option<OsString> {
+    ENV.read().unwrap_or_else(PoisonError::into_inner).get(k).cloned()
 }
 
-pub fn setenv(_: &OsStr, _: &OsStr) -> io::Result<()> {
-    Err(io::const_io_error!(io::ErrorKind::Unsupported, "cannot set env vars on this platform"))
+pub fn setenv(k: &OsStr, v: &OsStr) -> io::Result<()> {
+    let (k, v) = (k.to_owned(), v.to_owned());
+    ENV.write().unwrap_or_else(PoisonError::into_inner).insert(k, v);
+    Ok(())
 }
 
-pub fn unsetenv(_: &OsStr) -> io::Result<()> {
-    Err(io::const_io_error!(io::ErrorKind::Unsupported, "cannot unset env vars on this platform"))
+pub fn unsetenv(k: &OsStr) -> io::Result<()> {
+    ENV.write().unwrap_or_else(PoisonError::into_inner).remove(k);
+    Ok(())
 }
 
 pub fn temp_dir() -> PathBuf {
@@ -1,6 +1,10 @@
-use hyperion_abi::sys::fs::FileDesc;
+use hyperion_abi::sys::err::Error;
+use hyperion_abi::sys::fs::{FileDesc, PollEvents, PollFd};
+use hyperion_abi::sys::poll;
 
 use crate::io::{self, BorrowedCursor, IoSlice, IoSliceMut};
+use crate::os::hyperion::map_sys_err;
+use crate::sys_common::{AsInner, IntoInner};
 
 use super::fs::File;
 
@@ -57,15 +61,58 @@ pub fn write_vectored(&self, bufs: &[IoSlice<'_>]) -> io::Result<usize> {
     pub fn is_write_vectored(&self) -> bool {
         self.0.is_write_vectored()
     }
+}
+
+impl AsInner<File> for AnonPipe {
+    fn as_inner(&self) -> &File {
+        &self.0
+    }
+}
 
-    pub fn diverge(&self) -> ! {
-        todo!()
+impl IntoInner<File> for AnonPipe {
+    fn into_inner(self) -> File {
+        self.0
     }
 }
 
 pub fn read2(p1: AnonPipe, v1: &mut Vec<u8>, p2: AnonPipe, v2: &mut Vec<u8>) -> io::Result<()> {
-    // ??
-    p1.read_to_end(v1)?;
-    p2.read_to_end(v2)?;
-    Ok(())
+    // both pipes are read in the `poll` loop below, neither may block it
+    p1.0.set_nonblocking(true)?;
+    p2.0.set_nonblocking(true)?;
+
+    let pollfd = |pipe: &AnonPipe| PollFd {
+        fd: *pipe.0.as_inner(),
+        events: PollEvents::READ,
+        revents: PollEvents::empty(),
+    };
+    let mut fds = [pollfd(&p1), pollfd(&p2)];
+    loop {
+        // wait for either pipe to become readable
+        match poll(&mut fds, None) {
+            Ok(_) => {}
+            Err(err) if err == Error::INTERRUPTED => continue,
+            Err(err) => return Err(map_sys_err(err)),
+        }
+
+        // once one pipe is at EOF, the rest of the other one can be read
+        // while blocking
+        if !fds[0].revents.is_empty() && read(&p1, v1)? {
+            p2.0.set_nonblocking(false)?;
+            return p2.read_to_end(v2).map(drop);
+        }
+        if !fds[1].revents.is_empty() && read(&p2, v2)? {
+            p1.0.set_nonblocking(false)?;
+            return p1.read_to_end(v1).map(drop);
+        }
+    }
+
+    /// Reads everything that is available, returns whether the pipe is at
+    /// EOF. What was read before a `WouldBlock` is already in `dst`.
+    fn read(pipe: &AnonPipe, dst: &mut Vec<u8>) -> io::Result<bool> {
+        match pipe.read_to_end(dst) {
+            Ok(_) => Ok(true),
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => Ok(false),
+            Err(err) => Err(err),
+        }
+    }
 }
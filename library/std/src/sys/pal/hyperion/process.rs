@@ -6,15 +6,14 @@
 use crate::ffi::OsStr;
 use crate::fmt;
 use crate::io;
-use crate::marker::PhantomData;
-use crate::num::NonZeroI32;
+use crate::num::{NonZeroI32, NonZeroI64};
 use crate::os::hyperion::map_sys_err;
-use crate::path::Path;
+use crate::path::{Path, PathBuf};
+use crate::slice;
 use crate::sys::fs::File;
 use crate::sys::fs::OpenOptions;
 use crate::sys::pipe::AnonPipe;
-use crate::sys::unsupported;
-use crate::sys_common::AsInner;
+use crate::sys_common::{AsInner, IntoInner};
 use crate::sys_common::{
     process::{CommandEnv, CommandEnvs},
     FromInner,
@@ -30,6 +29,7 @@ pub struct Command {
     env: CommandEnv,
 
     program: String,
+    cwd: Option<PathBuf>,
     stdin: Option<Stdio>,
     stdout: Option<Stdio>,
     stderr: Option<Stdio>,
@@ -44,13 +44,21 @@ pub struct StdioPipes {
     pub stderr: Option<AnonPipe>,
 }
 
-// FIXME: This should be a unit struct, so we can always construct it
-// The value here should be never used, since we cannot spawn processes.
-#[derive(Clone, Copy)]
 pub enum Stdio {
     Inherit,
     Null,
     MakePipe,
+    // the Command keeps owning the fd, so it can be spawned more than once
+    Fd(File),
+    // std's own stdout or stderr, which is never closed
+    StaticFd(FileDesc),
+}
+
+// the fd handed to the child, the parent's copy of a new pipe end is closed
+// once the child has been spawned
+enum ChildStdio {
+    Explicit(FileDesc),
+    Owned(File),
 }
 
 impl Command {
@@ -59,6 +67,7 @@ pub fn new(program: &OsStr) -> Command {
             env: Default::default(),
 
             program: program.to_str().expect("program name should be UTF-8").to_string(),
+            cwd: None,
             stdin: None,
             stdout: None,
             stderr: None,
@@ -74,8 +83,8 @@ pub fn env_mut(&mut self) -> &mut CommandEnv {
         &mut self.env
     }
 
-    pub fn cwd(&mut self, _dir: &OsStr) {
-        panic!("hyperion doesn't have os enforced working directories");
+    pub fn cwd(&mut self, dir: &OsStr) {
+        self.cwd = Some(PathBuf::from(dir));
     }
 
     pub fn stdin(&mut self, stdin: Stdio) {
@@ -91,11 +100,11 @@ pub fn stderr(&mut self, stderr: Stdio) {
     }
 
     pub fn get_program(&self) -> &OsStr {
-        panic!("unsupported")
+        OsStr::new(&self.program)
     }
 
     pub fn get_args(&self) -> CommandArgs<'_> {
-        CommandArgs { _p: PhantomData }
+        CommandArgs { iter: self.args.iter() }
     }
 
     pub fn get_envs(&self) -> CommandEnvs<'_> {
@@ -103,127 +112,160 @@ pub fn get_envs(&self) -> CommandEnvs<'_> {
     }
 
     pub fn get_current_dir(&self) -> Option<&Path> {
-        None
+        self.cwd.as_deref()
     }
 
     pub fn spawn(
         &mut self,
         default: Stdio,
-        _needs_stdin: bool,
+        needs_stdin: bool,
     ) -> io::Result<(Process, StdioPipes)> {
-        let stdin = self.stdin.unwrap_or(default);
-        let stdout = self.stdout.unwrap_or(default);
-        let stderr = self.stderr.unwrap_or(default);
-
-        struct LazyNull(Option<File>);
-
-        impl LazyNull {
-            fn get(&mut self) -> FileDesc {
-                *self
-                    .0
-                    .get_or_insert_with(|| {
-                        File::open(
-                            "/dev/null".as_ref(),
-                            &OpenOptions::from_flags(FileOpenFlags::READ_WRITE),
-                        )
-                        .unwrap()
-                    })
-                    .as_inner()
-            }
-        }
-
-        let mut null = LazyNull(None);
-
-        let mut pipes = StdioPipes { stdin: None, stdout: None, stderr: None };
-
-        let stdin = match stdin {
-            Stdio::Inherit => FileDesc(0),
-            Stdio::Null => null.get(),
-            Stdio::MakePipe => {
-                let [r, w] = pipe().unwrap();
-                pipes.stdin = Some(AnonPipe::from_fd(r));
-                w
-            }
+        // `output` has nothing to write to the child's stdin
+        let default_stdin = if needs_stdin { &default } else { &Stdio::Null };
+        let stdin = self.stdin.as_ref().unwrap_or(default_stdin);
+        let stdout = self.stdout.as_ref().unwrap_or(&default);
+        let stderr = self.stderr.as_ref().unwrap_or(&default);
+
+        // the child resolves its relative paths against this directory
+        let cwd = match &self.cwd {
+            Some(cwd) => Some(crate::sys::fs::canonicalize(cwd)?),
+            None => None,
         };
-        let stdout = match stdout {
-            Stdio::Inherit => FileDesc(1),
-            Stdio::Null => null.get(),
-            Stdio::MakePipe => {
-                let [r, w] = pipe().unwrap();
-                pipes.stdout = Some(AnonPipe::from_fd(w));
-                r
-            }
-        };
-        let stderr = match stderr {
-            Stdio::Inherit => FileDesc(2),
-            Stdio::Null => null.get(),
-            Stdio::MakePipe => {
-                let [r, w] = pipe().unwrap();
-                pipes.stderr = Some(AnonPipe::from_fd(w));
-                r
-            }
+        let cwd = match &cwd {
+            Some(cwd) => Some(cwd.to_str().ok_or(io::const_io_error!(
+                io::ErrorKind::InvalidFilename,
+                "the working directory should be UTF-8"
+            ))?),
+            None => None,
         };
 
+        // std owns the environment, so the child always gets a full copy of it
+        let env = self
+            .env
+            .capture()
+            .into_iter()
+            .map(|(k, v)| match (k.into_string(), v.into_string()) {
+                (Ok(k), Ok(v)) => Ok(format!("{k}={v}")),
+                _ => Err(io::const_io_error!(
+                    io::ErrorKind::InvalidInput,
+                    "env vars should be UTF-8"
+                )),
+            })
+            .collect::<io::Result<Vec<String>>>()?;
+        let env: Vec<&str> = env.iter().map(|s| s.as_str()).collect();
+
+        let mut null = LazyNull(None);
+        let (stdin, our_stdin) = stdin.to_child_stdio(FileDesc(0), true, &mut null)?;
+        let (stdout, our_stdout) = stdout.to_child_stdio(FileDesc(1), false, &mut null)?;
+        let (stderr, our_stderr) = stderr.to_child_stdio(FileDesc(2), false, &mut null)?;
+        let pipes = StdioPipes { stdin: our_stdin, stdout: our_stdout, stderr: our_stderr };
+
         let args: Vec<&str> = self.args.iter().map(|s| s.as_str()).collect();
 
         let result = hyperion_abi::sys::system_with(
             self.program.as_str(),
             &args,
-            LaunchConfig { stdin, stdout, stderr },
+            LaunchConfig {
+                stdin: stdin.fd(),
+                stdout: stdout.fd(),
+                stderr: stderr.fd(),
+                cwd,
+                env: &env,
+            },
         );
 
-        if pipes.stdin.is_some() {
-            File::from_inner(stdin);
-        }
-        if pipes.stdout.is_some() {
-            File::from_inner(stdout);
-        }
-        if pipes.stderr.is_some() {
-            File::from_inner(stderr);
-        }
+        // the child has its own copies now, close ours of the child's pipe ends
+        drop((stdin, stdout, stderr));
 
         let pid: usize = result.map_err(map_sys_err)?;
 
-        Ok((Process(pid), pipes))
+        Ok((Process { pid, status: None }, pipes))
     }
 
     pub fn output(&mut self) -> io::Result<(ExitStatus, Vec<u8>, Vec<u8>)> {
-        unsupported()
+        let (proc, pipes) = self.spawn(Stdio::MakePipe, false)?;
+        crate::sys_common::process::wait_with_output(proc, pipes)
+    }
+}
+
+impl Stdio {
+    fn to_child_stdio(
+        &self,
+        inherit: FileDesc,
+        readable: bool,
+        null: &mut LazyNull,
+    ) -> io::Result<(ChildStdio, Option<AnonPipe>)> {
+        match self {
+            Stdio::Inherit => Ok((ChildStdio::Explicit(inherit), None)),
+            Stdio::Null => Ok((ChildStdio::Explicit(null.get()?), None)),
+            Stdio::MakePipe => {
+                let [r, w] = pipe().map_err(map_sys_err)?;
+                // the child reads its stdin and writes its stdout and stderr
+                let (ours, theirs) = if readable { (w, r) } else { (r, w) };
+                Ok((ChildStdio::Owned(File::from_inner(theirs)), Some(AnonPipe::from_fd(ours))))
+            }
+            Stdio::Fd(file) => Ok((ChildStdio::Explicit(*file.as_inner()), None)),
+            Stdio::StaticFd(fd) => Ok((ChildStdio::Explicit(*fd), None)),
+        }
+    }
+}
+
+impl ChildStdio {
+    fn fd(&self) -> FileDesc {
+        match self {
+            ChildStdio::Explicit(fd) => *fd,
+            ChildStdio::Owned(file) => *file.as_inner(),
+        }
+    }
+}
+
+// /dev/null is opened at most once per spawn, and only if it is needed
+struct LazyNull(Option<File>);
+
+impl LazyNull {
+    fn get(&mut self) -> io::Result<FileDesc> {
+        if let Some(file) = &self.0 {
+            return Ok(*file.as_inner());
+        }
+        let file =
+            File::open("/dev/null".as_ref(), &OpenOptions::from_flags(FileOpenFlags::READ_WRITE))?;
+        Ok(*self.0.insert(file).as_inner())
     }
 }
 
 impl From<AnonPipe> for Stdio {
     fn from(pipe: AnonPipe) -> Stdio {
-        pipe.diverge()
+        Stdio::Fd(pipe.into_inner())
     }
 }
 
 impl From<io::Stdout> for Stdio {
     fn from(_: io::Stdout) -> Stdio {
-        // FIXME: This is wrong.
-        // Instead, the Stdio we have here should be a unit struct.
-        panic!("unsupported")
+        Stdio::StaticFd(FileDesc(1))
     }
 }
 
 impl From<io::Stderr> for Stdio {
     fn from(_: io::Stderr) -> Stdio {
-        // FIXME: This is wrong.
-        // Instead, the Stdio we have here should be a unit struct.
-        panic!("unsupported")
+        Stdio::StaticFd(FileDesc(2))
     }
 }
 
 impl From<File> for Stdio {
-    fn from(_file: File) -> Stdio {
-        // FIXME: This is wrong.
-        // Instead, the Stdio we have here should be a unit struct.
-        panic!("unsupported")
+    fn from(file: File) -> Stdio {
+        Stdio::Fd(file)
     }
 }
 
 impl fmt::Debug for Command {
-    fn fmt(&self, _f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(cwd) = &self.cwd {
+            write!(f, "cd {cwd:?} && ")?;
+        }
+        write!(f, "{:?}", self.program)?;
+        for arg in &self.args {
+            write!(f, " {arg:?}")?;
+        }
         Ok(())
     }
 }
@@ -234,53 +276,42 @@ fn fmt(&self, _f: &mut fmt::Formatter<'_>) -> fmt::Result {
 
 impl ExitStatus {
     pub fn exit_ok(&self) -> Result<(), ExitStatusError> {
-        Ok(())
+        match NonZeroI64::new(self.0) {
+            None => Ok(()),
+            Some(code) => Err(ExitStatusError(code)),
+        }
     }
 
     pub fn code(&self) -> Option<i32> {
-        Some(0)
+        Some(self.0 as i32)
     }
 }
 
 impl fmt::Display for ExitStatus {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "<dummy exit status>")
-    }
-}
-
-pub struct ExitStatusError(!);
-
-impl Clone for ExitStatusError {
-    fn clone(&self) -> ExitStatusError {
-        self.0
+        write!(f, "exit status: {}", self.0)
     }
 }
 
-impl Copy for ExitStatusError {}
-
-impl PartialEq for ExitStatusError {
-    fn eq(&self, _other: &ExitStatusError) -> bool {
-        self.0
-    }
-}
-
-impl Eq for ExitStatusError {}
+#[derive(PartialEq, Eq, Clone, Copy)]
+pub struct ExitStatusError(NonZeroI64);
 
 impl fmt::Debug for ExitStatusError {
-    fn fmt(&self, _f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        self.0
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("hyperion_exit_status").field(&self.0).finish()
     }
 }
 
 impl Into<ExitStatus> for ExitStatusError {
     fn into(self) -> ExitStatus {
-        self.0
+        ExitStatus(self.0.get())
     }
 }
 
 impl ExitStatusError {
     pub fn code(self) -> Option<NonZeroI32> {
-        self.0
+        // a non-zero exit code might still truncate to zero
+        NonZeroI32::new(self.0.get() as i32)
     }
 }
 
@@ -318,54 +349,71 @@ fn from_raw(raw: i64) -> Self {
     }
 }
 
-pub struct Process(usize);
+pub struct Process {
+    pid: usize,
+    status: Option<ExitStatus>,
+}
 
 impl Process {
     pub fn id(&self) -> u32 {
-        self.0 as u32
+        self.pid as u32
     }
 
     pub fn kill(&mut self) -> io::Result<()> {
-        todo!()
+        // If we've already waited on this process then the pid can be recycled
+        // and used for another process, and we probably shouldn't be killing
+        // random processes, so return Ok because the process has exited already.
+        if self.status.is_some() {
+            return Ok(());
+        }
+        hyperion_abi::sys::kill(self.pid).map_err(map_sys_err)
     }
 
     pub fn wait(&mut self) -> io::Result<ExitStatus> {
-        // FIXME: this is the most hacky way to wait for a process to close
-        // hyperion doesn't have a wait syscall yet
-        let path = format!("/proc/{}/status", self.id());
-
-        // lmao, just spin on the /proc/<id> directory, this is pure evil
-        while crate::fs::File::open(&path).is_ok() {
-            hyperion_abi::sys::nanosleep(10_000_000)
-            // hyperion_abi::sys::yield_now()
+        if let Some(status) = self.status {
+            return Ok(status);
         }
-
-        Ok(ExitStatus(0))
+        let code = hyperion_abi::sys::wait(self.pid).map_err(map_sys_err)?;
+        let status = ExitStatus(code);
+        self.status = Some(status);
+        Ok(status)
     }
 
     pub fn try_wait(&mut self) -> io::Result<Option<ExitStatus>> {
-        self.wait().map(Some)
+        if let Some(status) = self.status {
+            return Ok(Some(status));
+        }
+        let Some(code) = hyperion_abi::sys::try_wait(self.pid).map_err(map_sys_err)? else {
+            return Ok(None);
+        };
+        let status = ExitStatus(code);
+        self.status = Some(status);
+        Ok(Some(status))
     }
 }
 
 pub struct CommandArgs<'a> {
-    _p: PhantomData<&'a ()>,
+    iter: slice::Iter<'a, String>,
 }
 
 impl<'a> Iterator for CommandArgs<'a> {
     type Item = &'a OsStr;
     fn next(&mut self) -> Option<&'a OsStr> {
-        None
+        self.iter.next().map(OsStr::new)
     }
     fn size_hint(&self) -> (usize, Option<usize>) {
-        (0, Some(0))
+        self.iter.size_hint()
     }
 }
 
-impl<'a> ExactSizeIterator for CommandArgs<'a> {}
+impl<'a> ExactSizeIterator for CommandArgs<'a> {
+    fn len(&self) -> usize {
+        self.iter.len()
+    }
+}
 
 impl<'a> fmt::Debug for CommandArgs<'a> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.debug_list().finish()
+        f.debug_list().entries(self.iter.clone()).finish()
     }
 }
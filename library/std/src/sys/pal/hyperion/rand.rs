@@ -0,0 +1,56 @@
+use hyperion_abi::sys::get_random;
+
+use crate::io;
+use crate::mem;
+use crate::os::hyperion::map_sys_err;
+
+//
+
+/// Fills `buf` with random bytes.
+///
+/// The CPU's RDSEED and RDRAND instructions are used when the target enables
+/// them, the kernel's entropy pool is only asked for what they could not
+/// provide.
+pub fn fill_bytes(mut buf: &mut [u8]) -> io::Result<()> {
+    #[cfg(all(target_arch = "x86_64", target_feature = "rdrand", target_feature = "rdseed"))]
+    while !buf.is_empty() {
+        let Some(word) = hw::random_u64() else { break };
+        let n = buf.len().min(mem::size_of::<u64>());
+        buf[..n].copy_from_slice(&word.to_ne_bytes()[..n]);
+        buf = &mut mem::take(&mut buf)[n..];
+    }
+
+    while !buf.is_empty() {
+        let n = get_random(buf).map_err(map_sys_err)?;
+        buf = &mut mem::take(&mut buf)[n..];
+    }
+
+    Ok(())
+}
+
+#[cfg(all(target_arch = "x86_64", target_feature = "rdrand", target_feature = "rdseed"))]
+mod hw {
+    use crate::arch::x86_64::{_rdrand64_step, _rdseed64_step};
+
+    // Intel recommends giving up on RDRAND after 10 failed attempts, RDSEED
+    // fails more often when the entropy source is drained, so it falls back
+    // to RDRAND after the same amount
+    const RETRIES: usize = 10;
+
+    pub fn random_u64() -> Option<u64> {
+        let mut ret = 0;
+        for _ in 0..RETRIES {
+            // SAFETY: the target enables the rdseed feature
+            if unsafe { _rdseed64_step(&mut ret) } == 1 {
+                return Some(ret);
+            }
+        }
+        for _ in 0..RETRIES {
+            // SAFETY: the target enables the rdrand feature
+            if unsafe { _rdrand64_step(&mut ret) } == 1 {
+                return Some(ret);
+            }
+        }
+        None
+    }
+}
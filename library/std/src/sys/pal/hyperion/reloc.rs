@@ -0,0 +1,145 @@
+//! Self-relocation for static-PIE binaries.
+//!
+//! The kernel loads Hyperion binaries at a random address and jumps straight to
+//! `_start` without processing any relocations. Every pointer stored in the
+//! binary's data (vtables, `&'static` references in statics, ...) is still
+//! relative to address 0 at that point, so [`relocate`] has to run before
+//! anything reads one of them.
+//!
+//! Nothing in here may use a pointer from the binary's data, and nothing may
+//! panic: the panic machinery itself needs relocated data.
+
+use super::elf::{self, PT_DYNAMIC};
+use crate::intrinsics;
+
+//
+
+const DT_NULL: usize = 0;
+const DT_RELA: usize = 7;
+const DT_RELASZ: usize = 8;
+const DT_RELAENT: usize = 9;
+const DT_RELRSZ: usize = 35;
+const DT_RELR: usize = 36;
+const DT_RELRENT: usize = 37;
+
+// every architecture has its own number for the same relocation
+const R_NONE: u32 = 0;
+#[cfg(target_arch = "x86_64")]
+const R_RELATIVE: u32 = 8;
+#[cfg(target_arch = "aarch64")]
+const R_RELATIVE: u32 = 1027;
+#[cfg(target_arch = "riscv64")]
+const R_RELATIVE: u32 = 3;
+
+/// `Elf64_Rela`
+#[repr(C)]
+struct Rela {
+    offset: usize,
+    info: usize,
+    addend: isize,
+}
+
+//
+
+/// Applies the binary's relative relocations.
+///
+/// Does nothing for binaries that weren't linked as static-PIE or that were
+/// loaded at their link address.
+///
+/// # Safety
+///
+/// Has to be called exactly once, before anything reads a pointer from the
+/// binary's data.
+#[inline(never)]
+pub unsafe fn relocate() {
+    let Some(base) = elf::load_bias() else { return };
+    let Some(dynamic) = elf::phdrs().iter().find(|phdr| phdr.ty == PT_DYNAMIC) else { return };
+    if base != 0 {
+        // SAFETY: `PT_DYNAMIC` is the link address of `_DYNAMIC`
+        unsafe { apply(base, base + dynamic.vaddr) };
+    }
+}
+
+/// # Safety
+///
+/// `dynamic` has to be the runtime address of the `_DYNAMIC` array of a
+/// binary loaded at `base`.
+unsafe fn apply(base: usize, dynamic: usize) {
+    let (mut rela, mut rela_len, mut rela_ent) = (0, 0, 0);
+    let (mut relr, mut relr_len, mut relr_ent) = (0, 0, 0);
+
+    // SAFETY: `_DYNAMIC` is a `DT_NULL` terminated array of tag and value pairs
+    let mut entry = dynamic as *const [usize; 2];
+    unsafe {
+        while (*entry)[0] != DT_NULL {
+            let [tag, val] = *entry;
+            match tag {
+                DT_RELA => rela = base + val,
+                DT_RELASZ => rela_len = val,
+                DT_RELAENT => rela_ent = val,
+                DT_RELR => relr = base + val,
+                DT_RELRSZ => relr_len = val,
+                DT_RELRENT => relr_ent = val,
+                _ => {}
+            }
+            entry = entry.add(1);
+        }
+    }
+
+    if rela != 0 {
+        if rela_ent != crate::mem::size_of::<Rela>() {
+            intrinsics::abort();
+        }
+        let mut at = rela;
+        while at < rela + rela_len {
+            // SAFETY: the linker only emits relocations within the binary
+            unsafe {
+                let rela = &*(at as *const Rela);
+                match rela.info as u32 {
+                    R_NONE => {}
+                    R_RELATIVE => {
+                        *((base + rela.offset) as *mut usize) =
+                            base.wrapping_add_signed(rela.addend)
+                    }
+                    // a static-PIE binary can't have any other relocations
+                    _ => intrinsics::abort(),
+                }
+            }
+            at += rela_ent;
+        }
+    }
+
+    // `DT_RELR` packs relative relocations into addresses and bitmaps of the
+    // words following them
+    if relr != 0 {
+        const WORD: usize = crate::mem::size_of::<usize>();
+        if relr_ent != WORD {
+            intrinsics::abort();
+        }
+        let mut next = 0;
+        let mut at = relr;
+        while at < relr + relr_len {
+            // SAFETY: as above
+            unsafe {
+                let entry = *(at as *const usize);
+                if entry & 1 == 0 {
+                    let word = (base + entry) as *mut usize;
+                    *word = (*word).wrapping_add(base);
+                    next = word.addr() + WORD;
+                } else {
+                    let mut bitmap = entry >> 1;
+                    let mut word = next as *mut usize;
+                    while bitmap != 0 {
+                        if bitmap & 1 != 0 {
+                            *word = (*word).wrapping_add(base);
+                        }
+                        bitmap >>= 1;
+                        word = word.add(1);
+                    }
+                    next += (usize::BITS as usize - 1) * WORD;
+                }
+            }
+            at += WORD;
+        }
+    }
+}
@@ -0,0 +1,34 @@
+#![cfg_attr(test, allow(dead_code))]
+
+use hyperion_abi::sys::set_fault_handler;
+
+use crate::sys_common::thread_info;
+
+//
+
+// Fault handler for page faults the process can't recover from. The kernel
+// keeps an unmapped guard page below every thread stack, so a thread that
+// runs into it ends up here. The guard page is part of the `spawn_with_stack`
+// contract, see `thread::guard`. We want to detect these cases and print out a
+// helpful error saying that the stack has overflowed, instead of a generic
+// page fault.
+//
+// The kernel runs the handler on a separate fault stack, and kills the
+// process once it returns, like it would have done without a handler.
+extern "C" fn fault_handler(addr: usize) {
+    let guard = thread_info::stack_guard().unwrap_or(0..0);
+
+    // If the faulting address is within the guard page, then we print a
+    // message saying so and abort.
+    if guard.contains(&addr) {
+        let thread = thread_info::current_thread();
+        let name = thread.as_ref().and_then(|t| t.name()).unwrap_or("<unknown>");
+        rtprintpanic!("\nthread '{name}' has overflowed its stack\n");
+        rtabort!("stack overflow");
+    }
+}
+
+pub unsafe fn init() {
+    // nothing can be reported without the handler, a failure isn't fatal
+    _ = set_fault_handler(fault_handler);
+}
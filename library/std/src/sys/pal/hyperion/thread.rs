@@ -1,35 +1,70 @@
-use hyperion_abi::sys::{done, nanosleep, rename, spawn, yield_now};
+use hyperion_abi::sys::{cpu_count, done, nanosleep, rename, spawn_with_stack, yield_now};
 
-use super::unsupported;
+use super::thread_local_dtor::run_dtors;
 use crate::boxed::Box;
 use crate::ffi::CStr;
 use crate::io;
 use crate::num::NonZeroUsize;
+use crate::os::hyperion::map_sys_err;
 use crate::ptr;
+use crate::sync::atomic::{AtomicUsize, Ordering};
+use crate::sync::Arc;
+use crate::sys::futex::{futex_wait, futex_wake_all};
 use crate::time::Duration;
 
 //
 
-pub struct Thread();
+pub struct Thread {
+    packet: Arc<Packet>,
+}
+
+/// Shared between the [`Thread`] handle and the thread itself.
+struct Packet {
+    /// `RUNNING` until the thread has returned from its main fn and ran its TLS destructors
+    state: AtomicUsize,
+}
 
-pub const DEFAULT_MIN_STACK_SIZE: usize = 4096;
+const RUNNING: usize = 0;
+const FINISHED: usize = 1;
+
+// a single page is not even enough for the stack probes of an average frame
+pub const DEFAULT_MIN_STACK_SIZE: usize = 1 << 20;
 
 impl Thread {
     // unsafe: see thread::Builder::spawn_unchecked for safety requirements
-    pub unsafe fn new(_stack: usize, p: Box<dyn FnOnce()>) -> io::Result<Thread> {
-        let p: *mut Box<dyn FnOnce()> = Box::into_raw(Box::new(p));
-        spawn(_thread_entry, p.expose_addr());
+    pub unsafe fn new(stack: usize, p: Box<dyn FnOnce()>) -> io::Result<Thread> {
+        let packet = Arc::new(Packet { state: AtomicUsize::new(RUNNING) });
+        let data: Box<(Box<dyn FnOnce()>, Arc<Packet>)> = Box::new((p, packet.clone()));
+        let data: *mut (Box<dyn FnOnce()>, Arc<Packet>) = Box::into_raw(data);
+
+        // std doesn't map thread stacks itself, `spawn_with_stack` has the
+        // kernel map `stack` bytes with an unmapped guard page right below
+        // them, which stays unmapped until the thread exits
+        let stack = stack.max(DEFAULT_MIN_STACK_SIZE);
+        if let Err(err) = spawn_with_stack(_thread_entry, data.expose_addr(), stack) {
+            // The thread failed to start and as a result `data` was not consumed.
+            drop(unsafe { Box::from_raw(data) });
+            return Err(map_sys_err(err));
+        }
 
         extern "C" fn _thread_entry(_stack_ptr: usize, arg: usize) -> ! {
-            let p: *mut Box<dyn FnOnce()> = ptr::from_exposed_addr_mut(arg);
-            let main: Box<dyn FnOnce()> = *unsafe { Box::from_raw(p) };
+            let data: *mut (Box<dyn FnOnce()>, Arc<Packet>) = ptr::from_exposed_addr_mut(arg);
+            let (main, packet) = *unsafe { Box::from_raw(data) };
 
             main();
 
+            // run all destructors
+            unsafe { run_dtors() };
+
+            packet.state.store(FINISHED, Ordering::Release);
+            futex_wake_all(&packet.state);
+            // `done` never returns, so the packet has to be dropped manually
+            drop(packet);
+
             done(0);
         }
 
-        Ok(Thread())
+        Ok(Thread { packet })
     }
 
     pub fn yield_now() {
@@ -45,20 +80,31 @@ pub fn sleep(dur: Duration) {
     }
 
     pub fn join(self) {
-        unsupported().unwrap()
+        while self.packet.state.load(Ordering::Acquire) == RUNNING {
+            futex_wait(&self.packet.state, RUNNING, None);
+        }
     }
 }
 
 pub fn available_parallelism() -> io::Result<NonZeroUsize> {
-    unsupported()
+    NonZeroUsize::new(cpu_count()).ok_or(io::const_io_error!(
+        io::ErrorKind::NotFound,
+        "the number of hardware threads is not known for the target platform"
+    ))
 }
 
 pub mod guard {
-    pub type Guard = !;
+    use crate::ops::Range;
+
+    pub type Guard = Range<usize>;
+
+    // every stack the kernel hands out, the main thread's and the ones from
+    // `spawn_with_stack`, has an unmapped guard page below it, which
+    // `stack_guard` reports for the calling thread
     pub unsafe fn current() -> Option<Guard> {
-        None
+        hyperion_abi::sys::stack_guard().ok()
     }
     pub unsafe fn init() -> Option<Guard> {
-        None
+        unsafe { current() }
     }
 }
@@ -1,7 +1,6 @@
-use hyperion_abi::sys::{futex_wait, futex_wake};
-
 use crate::pin::Pin;
 use crate::sync::atomic::{AtomicUsize, Ordering::*};
+use crate::sys::futex::{futex_wait, futex_wake};
 use crate::time::Duration;
 
 //
@@ -55,7 +54,7 @@ pub unsafe fn park(self: Pin<&Self>) {
         }
         loop {
             // Wait for something to happen, assuming it's still set to PARKED.
-            futex_wait(&self.state, PARKED);
+            futex_wait(&self.state, PARKED, None);
             // Change NOTIFIED=>EMPTY and return in that case.
             if self.state.compare_exchange(NOTIFIED, EMPTY, Acquire, Acquire).is_ok() {
                 return;
@@ -68,10 +67,8 @@ pub unsafe fn park(self: Pin<&Self>) {
     // Assumes this is only called by the thread that owns the Parker,
     // which means that `self.state != PARKED`. This implementation doesn't
     // require `Pin`, but other implementations do.
-    pub unsafe fn park_timeout(self: Pin<&Self>, _timeout: Duration) {
-        // FIXME: futex_wait with a timeout in hyperion
-        hyperion_abi::sys::yield_now();
-        /* // Change NOTIFIED=>EMPTY or EMPTY=>PARKED, and directly return in the
+    pub unsafe fn park_timeout(self: Pin<&Self>, timeout: Duration) {
+        // Change NOTIFIED=>EMPTY or EMPTY=>PARKED, and directly return in the
         // first case.
         if self.state.fetch_sub(1, Acquire) == NOTIFIED {
             return;
@@ -86,7 +83,7 @@ pub unsafe fn park_timeout(self: Pin<&Self>, _timeout: Duration) {
             // Timeout or spurious wake up.
             // We return either way, because we can't easily tell if it was the
             // timeout or not.
-        } */
+        }
     }
 
     // This implementation doesn't require `Pin`, but other implementations do.
@@ -99,7 +96,7 @@ pub fn unpark(self: Pin<&Self>) {
         // purpose, to make sure every unpark() has a release-acquire ordering
         // with park().
         if self.state.swap(NOTIFIED, Release) == PARKED {
-            futex_wake(&self.state, 1);
+            futex_wake(&self.state);
         }
     }
 }
@@ -1,4 +1,4 @@
-use hyperion_abi::sys::timestamp;
+use hyperion_abi::sys::{system_time, timestamp};
 
 use crate::time::Duration;
 
@@ -30,11 +30,26 @@ pub fn checked_add_duration(&self, other: &Duration) -> Option<Instant> {
     pub fn checked_sub_duration(&self, other: &Duration) -> Option<Instant> {
         Some(Instant(self.0.checked_sub(*other)?))
     }
+
+    /// Nanoseconds since boot, the clock the kernel uses for deadlines.
+    pub(crate) fn as_nanos(&self) -> u64 {
+        self.0.as_nanos().try_into().unwrap_or(u64::MAX)
+    }
 }
 
 impl SystemTime {
     pub fn now() -> SystemTime {
-        panic!("time not implemented on this platform")
+        let now = system_time().unwrap();
+        SystemTime(Duration::from_nanos(now.try_into().unwrap()))
+    }
+
+    /// `nanos` since the unix epoch, the format the kernel uses for wall-clock time.
+    pub(crate) fn from_nanos(nanos: u64) -> SystemTime {
+        SystemTime(Duration::from_nanos(nanos))
+    }
+
+    pub(crate) fn as_nanos(&self) -> Option<u64> {
+        self.0.as_nanos().try_into().ok()
     }
 
     pub fn sub_time(&self, other: &SystemTime) -> Result<Duration, Duration> {
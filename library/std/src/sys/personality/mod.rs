@@ -29,6 +29,7 @@ fn rust_eh_personality() {
         all(target_family = "windows", target_env = "gnu"),
         target_os = "psp",
         target_os = "xous",
+        target_os = "hyperion",
         target_os = "solid_asp3",
         all(target_family = "unix", not(target_os = "espidf"), not(target_os = "l4re")),
         all(target_vendor = "fortanix", target_env = "sgx"),
@@ -89,6 +89,11 @@ pub unsafe fn _Unwind_GetIPInfo(
     unsafe { &*(unwinding::abi::_Unwind_GetIPInfo(ctx, ip_before_insn) as _Unwind_Word) }
 }
 
+pub unsafe fn _Unwind_GetIP(ctx: *mut _Unwind_Context) -> _Unwind_Word {
+    let ctx = unsafe { &mut *(ctx as *mut UnwindContext<'_>) };
+    unwinding::abi::_Unwind_GetIP(ctx) as _Unwind_Word
+}
+
 pub unsafe fn _Unwind_GetLanguageSpecificData(ctx: *mut _Unwind_Context) -> *mut c_void {
     let ctx = unsafe { &mut *(ctx as *mut UnwindContext<'_>) };
     unwinding::abi::_Unwind_GetLanguageSpecificData(ctx)
@@ -103,3 +108,15 @@ pub unsafe fn _Unwind_DeleteException(exception: *mut _Unwind_Exception) {
     let exception = unsafe { &mut *(exception as *mut UnwindException) };
     unsafe { unwinding::abi::_Unwind_DeleteException(exception) }
 }
+
+pub type _Unwind_Trace_Fn =
+    extern "C" fn(ctx: *mut _Unwind_Context, arg: *mut c_void) -> _Unwind_Reason_Code;
+
+pub unsafe fn _Unwind_Backtrace(
+    trace: _Unwind_Trace_Fn,
+    trace_argument: *mut c_void,
+) -> _Unwind_Reason_Code {
+    // the context is passed by pointer either way
+    let trace: unwinding::abi::UnwindTraceFn = unsafe { core::mem::transmute(trace) };
+    unsafe { core::mem::transmute(unwinding::abi::_Unwind_Backtrace(trace, trace_argument)) }
+}
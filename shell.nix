@@ -15,7 +15,7 @@ let
     gdb = "${pkgs.gdb}/bin/gdb"
     python = "${pkgs.python3Full}/bin/python"
     host = ["x86_64-unknown-linux-gnu"]
-    target = ["x86_64-unknown-linux-gnu", "x86_64-unknown-hyperion", "x86_64-unknown-none"]
+    target = ["x86_64-unknown-linux-gnu", "x86_64-unknown-hyperion", "x86_64-unknown-hyperion-kernel"]
 
 
     [rust]
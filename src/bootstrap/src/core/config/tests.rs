@@ -1,5 +1,5 @@
 use super::{flags::Flags, Config};
-use crate::core::config::{LldMode, TomlConfig};
+use crate::core::config::{LldMode, Target, TomlConfig};
 
 use clap::CommandFactory;
 use serde::Deserialize;
@@ -231,3 +231,12 @@ fn rust_lld() {
     assert!(matches!(parse("rust.use-lld = true").lld_mode, LldMode::External));
     assert!(matches!(parse("rust.use-lld = false").lld_mode, LldMode::Unused));
 }
+
+#[test]
+fn hyperion_only_kernel_is_no_std() {
+    assert!(Target::from_triple("x86_64-unknown-hyperion-kernel").no_std);
+    assert!(!Target::from_triple("x86_64-unknown-hyperion").no_std);
+    assert!(!Target::from_triple("aarch64-unknown-hyperion").no_std);
+    assert!(!Target::from_triple("riscv64gc-unknown-hyperion").no_std);
+    assert!(!Target::from_triple("x86_64-unknown-hyperion-hosted").no_std);
+}
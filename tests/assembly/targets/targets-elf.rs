@@ -30,6 +30,9 @@
 // revisions: aarch64_unknown_hermit
 // [aarch64_unknown_hermit] compile-flags: --target aarch64-unknown-hermit
 // [aarch64_unknown_hermit] needs-llvm-components: aarch64
+// revisions: aarch64_unknown_hyperion
+// [aarch64_unknown_hyperion] compile-flags: --target aarch64-unknown-hyperion
+// [aarch64_unknown_hyperion] needs-llvm-components: aarch64
 // revisions: aarch64_unknown_illumos
 // [aarch64_unknown_illumos] compile-flags: --target aarch64-unknown-illumos
 // [aarch64_unknown_illumos] needs-llvm-components: aarch64
@@ -399,6 +402,9 @@
 // revisions: riscv64gc_unknown_hermit
 // [riscv64gc_unknown_hermit] compile-flags: --target riscv64gc-unknown-hermit
 // [riscv64gc_unknown_hermit] needs-llvm-components: riscv
+// revisions: riscv64gc_unknown_hyperion
+// [riscv64gc_unknown_hyperion] compile-flags: --target riscv64gc-unknown-hyperion
+// [riscv64gc_unknown_hyperion] needs-llvm-components: riscv
 // revisions: riscv64gc_unknown_linux_gnu
 // [riscv64gc_unknown_linux_gnu] compile-flags: --target riscv64gc-unknown-linux-gnu
 // [riscv64gc_unknown_linux_gnu] needs-llvm-components: riscv
@@ -525,6 +531,15 @@
 // revisions: x86_64_unknown_hermit
 // [x86_64_unknown_hermit] compile-flags: --target x86_64-unknown-hermit
 // [x86_64_unknown_hermit] needs-llvm-components: x86
+// revisions: x86_64_unknown_hyperion
+// [x86_64_unknown_hyperion] compile-flags: --target x86_64-unknown-hyperion
+// [x86_64_unknown_hyperion] needs-llvm-components: x86
+// revisions: x86_64_unknown_hyperion_hosted
+// [x86_64_unknown_hyperion_hosted] compile-flags: --target x86_64-unknown-hyperion-hosted
+// [x86_64_unknown_hyperion_hosted] needs-llvm-components: x86
+// revisions: x86_64_unknown_hyperion_kernel
+// [x86_64_unknown_hyperion_kernel] compile-flags: --target x86_64-unknown-hyperion-kernel
+// [x86_64_unknown_hyperion_kernel] needs-llvm-components: x86
 // revisions: x86_64_unknown_illumos
 // [x86_64_unknown_illumos] compile-flags: --target x86_64-unknown-illumos
 // [x86_64_unknown_illumos] needs-llvm-components: x86
@@ -0,0 +1,22 @@
+// Pointers in statics, vtables and thread-local initializers need relative
+// relocations.
+use std::cell::Cell;
+use std::thread;
+
+static GREETING: &str = "hello from a relocated binary";
+static WRITERS: &[fn() -> Box<dyn std::fmt::Display>] = &[|| Box::new(GREETING)];
+
+thread_local! {
+    static LOCAL: Cell<&'static str> = const { Cell::new("hello from relocated TLS") };
+}
+
+fn main() {
+    for writer in WRITERS {
+        println!("{}", writer());
+    }
+    // the main thread's TLS is set up before `_start`, other threads' after it
+    println!("{}", LOCAL.get());
+    println!("{}", thread::spawn(|| LOCAL.get()).join().unwrap());
+    // differs between runs, the binary is loaded at a random address
+    println!("main = {:p}", main as fn());
+}
@@ -0,0 +1,9 @@
+// Pointers in statics and vtables need relative relocations.
+static GREETING: &str = "hello from a relocated binary";
+static WRITERS: &[fn() -> Box<dyn std::fmt::Display>] = &[|| Box::new(GREETING)];
+
+fn main() {
+    for writer in WRITERS {
+        println!("{}", writer());
+    }
+}